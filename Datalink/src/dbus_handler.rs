@@ -3,8 +3,14 @@ use dbus::{blocking::Connection, channel::Sender, Message};
 const INTERFACE_NAME:&str = "com.github.lukaslichten.datalink";
 const PATH_NAME:&str = "/com/github/lukaslichten/datalink";
 
+const LAUNCHING_SINGAL:&str = "Launching";
 const PLAYING_SINGAL:&str = "StartedPlaying";
 const STOPPED_SINGAL:&str = "StoppedPlaying";
+const APP_CRASHED_SINGAL:&str = "AppCrashed";
+
+pub(crate) fn set_launching(game_name: String) -> Option<()> {
+    send_state(game_name, LAUNCHING_SINGAL)
+}
 
 pub(crate) fn set_playing(game_name: String) -> Option<()> {
     send_state(game_name, PLAYING_SINGAL)
@@ -14,6 +20,18 @@ pub(crate) fn unset_playing(game_name: String) -> Option<()> {
     send_state(game_name, STOPPED_SINGAL)
 }
 
+/// Sends that an auxiliary app of the game exited with a failure (exit code -1 if unknown)
+pub(crate) fn app_crashed(game_name: String, app_name: String, exit_code: i32) -> Option<()> {
+    let c = Connection::new_session().ok()?;
+
+    let msg = Message::new_signal(PATH_NAME, INTERFACE_NAME, APP_CRASHED_SINGAL).ok()?;
+    let msg = msg.append3(game_name, app_name, exit_code);
+
+    c.send(msg).ok()?;
+
+    Some(())
+}
+
 /// Sends signal of name state with playload name
 fn send_state(game_name: String, state: &str) -> Option<()> {
     let c = Connection::new_session().ok()?;
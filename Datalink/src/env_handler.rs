@@ -33,21 +33,13 @@ pub fn do_env(gameid: &str) -> Result<(), String> {
 
 
 fn get_env_filepath(gameid: &str) -> Option<PathBuf> {
-    let mut buff = crate::get_config_folder()?;
-
-    // This may need to be spun into it's own, in case we add more configuarable options
-    buff.push(gameid);
-    if !buff.exists() {
-        fs::create_dir(buff.as_path()).ok()?;
-    } else if !buff.is_dir() {
-        return None;
-    }
-
+    let mut buff = crate::get_game_config_folder(gameid)?;
     buff.push("env");
     Some(buff)
 }
 
-fn read_env_file(file: PathBuf) -> Result<Vec<(String, String)>, String> {
+/// Reads a file of key=value lines, also used for other per game configs
+pub(crate) fn read_env_file(file: PathBuf) -> Result<Vec<(String, String)>, String> {
     let mut pairs = Vec::<(String, String)>::new();
 
     let text = fs::read_to_string(file.as_path()).map_err(|e| e.to_string())?;
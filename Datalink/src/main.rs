@@ -4,6 +4,10 @@ use env_handler::do_env;
 
 mod dbus_handler;
 mod env_handler;
+mod udp_relay;
+
+#[cfg(test)]
+mod test;
 
 mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
@@ -77,12 +81,34 @@ fn main() {
         }
     }
 
+    // Lets the bridge know about the override (for conditional apps)
+    if let Some(over) = exec_override.as_ref() {
+        unsafe {
+            std::env::set_var(OVERRIDE_ENV_VAR, over);
+        }
+    }
+
+    // Lets the bridge know where the policy is, so it can enforce it
+    if let Some(policy) = get_policy_path() {
+        unsafe {
+            std::env::set_var(POLICY_ENV_VAR, policy);
+        }
+    }
+
+    // Lets the bridge know which drive the linux root is mounted as in this prefix
+    if let Some(drive) = detect_root_drive() {
+        unsafe {
+            std::env::set_var(ROOT_DRIVE_ENV_VAR, drive.to_string());
+        }
+    }
+
     if is_proton {
         println!("Datalink prep for game {gameid} finished, switching into Proton...");
         let err = cmd.exec();
         panic!("Failed to launch proton: {}", err.to_string())
     } else {
         println!("Launching native Game {} as child", gameid.as_str());
+        dbus_handler::set_launching(gameid.clone());
         if let Ok(mut run) = cmd.spawn() {
             let file_opt = get_runningfile_path(gameid.as_str());
 
@@ -93,16 +119,20 @@ fn main() {
                 let _ = fs::write(f, std::process::id().to_string());
             }
             dbus_handler::set_playing(gameid.clone());
+            let relay = start_relay(gameid.as_str(), file_opt.as_ref());
 
 
             // Running the game
             let res = run.wait();
 
 
-            // Game exited, deleting running file and sending dbus message
+            // Game exited, deleting running file (which also stops the relay) and sending dbus message
             if let Some(f) = file_opt {
                 let _ = fs::remove_file(f);
             }
+            if let Some(relay) = relay {
+                let _ = relay.join();
+            }
             dbus_handler::unset_playing(gameid.clone());
 
 
@@ -111,6 +141,7 @@ fn main() {
                 Ok(_) => println!("Game shutdown, exiting Datalink")
             }
         } else {
+            dbus_handler::unset_playing(gameid.clone());
             panic!("Failed to start game");
         }
 
@@ -125,19 +156,19 @@ fn handle_instructions(args: &mut std::env::Args) -> Option<(String, Option<Stri
     if let Some(instr) = args.nth(1) {
         match instr.as_str() {
             "--help" => print_help(),
+            "--set-launching" => {
+                let game = args.next()?;
+                dbus_handler::set_launching(game)?;
+            },
             "--set-playing" => {
                 let game = args.next()?; // technically should error, but this is enough
-                
-                // Even if writing the cache file fails, we will still send the dbus message
-                if let Some(file) = get_runningfile_path(game.as_str()) {
-
-                    // When called like this we use the parent process
-                    // which when the call came (as expected) from the bridge.exe
-                    // will not be the bridge, but instead the pressure-vessel/wine
-                    fs::write(file, std::os::unix::process::parent_id().to_string()).ok()?;
-                }
-
-                dbus_handler::set_playing(game)?;
+                set_playing(game, args.next())?;
+            },
+            "--set-launching-playing" => {
+                // Both in one call, so they are sent in order
+                let game = args.next()?;
+                dbus_handler::set_launching(game.clone())?;
+                set_playing(game, args.next())?;
             },
             "--unset-playing" => {
                 let game = args.next()?;
@@ -151,6 +182,13 @@ fn handle_instructions(args: &mut std::env::Args) -> Option<(String, Option<Stri
 
                 dbus_handler::unset_playing(game)?;
             },
+            "--app-crashed" => {
+                let game = args.next()?;
+                let app = args.next()?;
+                let code = args.next().and_then(|c| c.parse::<i32>().ok()).unwrap_or(-1);
+
+                dbus_handler::app_crashed(game, app, code)?;
+            },
             "-D" => return handle_debug_flag(args),
             "-d" => return handle_debug_flag(args),
             "--debug" => return handle_debug_flag(args),
@@ -166,6 +204,32 @@ fn handle_instructions(args: &mut std::env::Args) -> Option<(String, Option<Stri
     None
 }
 
+/// Writes the running file and sends playing, then relays udp (if configured) until the session ends
+///
+/// The relay is configured by the Steam AppId (not subject to overrides), so it is only started
+/// if the bridge passed it (which it does for one of the game names)
+fn set_playing(game: String, app_id: Option<String>) -> Option<()> {
+    // Even if writing the cache file fails, we will still send the dbus message
+    let file_opt = get_runningfile_path(game.as_str());
+    if let Some(file) = file_opt.as_ref() {
+
+        // When called like this we use the parent process
+        // which when the call came (as expected) from the bridge.exe
+        // will not be the bridge, but instead the pressure-vessel/wine
+        fs::write(file, std::os::unix::process::parent_id().to_string()).ok()?;
+    }
+
+    dbus_handler::set_playing(game.clone())?;
+
+    // The bridge does not wait on us, so we can keep relaying until --unset-playing
+    // deletes the running file (or the process we wrote into it is gone)
+    if let Some(relay) = app_id.and_then(|id| start_relay(id.as_str(), file_opt.as_ref())) {
+        let _ = relay.join();
+    }
+
+    Some(())
+}
+
 fn check_if_debug_allowed() -> bool {
     if cfg!(feature = "include-debug") {
         true
@@ -243,6 +307,7 @@ Generally, if you want to modify the settings you can check within the prefix th
 /drive_c/users/steamuser/AppData/Roaming/Datalink/
 Changing the file ending away from json will disable them, further instructions on editing can be found here:
 https://github.com/LukasLichten/Datalink?tab=readme-ov-file#configuring-the-bridge
+The datalink-config tool lists, shows and edits these configs by the game's steam id (see datalink-config --help).
 
 If you want to change/set env variables for Proton/Native Game, 
 you can do this via ~/.config/Datalink/[gameid]/env
@@ -250,10 +315,24 @@ The folder will be automatically generated when launching the game with Datalink
 Important: Gameid is Not subject to overrides within the prefix, so use the true steamid.
 Each line is one Variable, seperated with an = between key and value. 
 You can use // and # for comments (but only at the beginning of lines!).
+
+As any programm can place a config within the prefix, you can restrict which configs
+may run apps and actions (and which folders those may touch) in ~/.config/Datalink/policy.json,
+see https://github.com/LukasLichten/Datalink?tab=readme-ov-file#config-policy
+
+If the game sends udp telemetry to a single port, a relay can forward it to multiple ports
+while the game is running, configured in ~/.config/Datalink/[gameid]/udp_relay:
+listen=20777
+forward=20778
+forward=20779
 ", built_info::PKG_VERSION_MAJOR, built_info::PKG_VERSION_MINOR, built_info::PKG_VERSION_PATCH, built_info::PKG_REPOSITORY);
 }
 
 fn get_runningfile_path(game: &str) -> Option<PathBuf> {
+    if !is_valid_folder_name(game) {
+        return None;
+    }
+
     let mut path = get_cache_folder()?;
     path.push("running");
 
@@ -267,8 +346,32 @@ fn get_runningfile_path(game: &str) -> Option<PathBuf> {
     Some(path)
 }
 
+/// Starts the udp relay for the session, logging any errors
+///
+/// Without a running file there is no way to tell when the session ends, so no relay is started
+fn start_relay(game: &str, running_file: Option<&PathBuf>) -> Option<std::thread::JoinHandle<()>> {
+    let running_file = running_file?;
+
+    match udp_relay::start_relay(game, running_file.clone()) {
+        Ok(relay) => relay,
+        Err(e) => {
+            println!("Datalink Failed to start udp relay: {}", e);
+            None
+        }
+    }
+}
+
 const DATALINK_FOLDER_NAME: &str = "Datalink";
 
+/// Has to match datalink_bridge_config::OVERRIDE_ENV_VAR
+const OVERRIDE_ENV_VAR: &str = "DATALINK_OVERRIDE";
+
+/// Has to match datalink_bridge_config::policy::POLICY_ENV_VAR
+const POLICY_ENV_VAR: &str = "DATALINK_POLICY";
+
+/// Has to match datalink_bridge_config::ROOT_DRIVE_ENV_VAR
+const ROOT_DRIVE_ENV_VAR: &str = "DATALINK_ROOT_DRIVE";
+
 /// This folder is ~/.cache/Datalink
 /// Used for storing the stage 2 .exe file and the running file (from the notification service)
 ///
@@ -305,6 +408,87 @@ pub fn get_config_folder() -> Option<PathBuf> {
     Some(folder)
 }
 
+/// This file is ~/.config/Datalink/policy.json
+/// Restricts what the configs within the prefix may do (enforced by the bridge)
+///
+/// None if it does not exist
+fn get_policy_path() -> Option<PathBuf> {
+    let mut path = get_config_folder()?;
+    path.push("policy.json");
+
+    if path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Finds the drive letter the linux root (`/`) is mounted as, through the `dosdevices`
+/// symlinks of the proton prefix (`STEAM_COMPAT_DATA_PATH/pfx`)
+///
+/// Z is preferred if multiple drives point at the root, None if there is no prefix or no such drive
+fn detect_root_drive() -> Option<char> {
+    let mut dosdevices = PathBuf::from(std::env::var_os("STEAM_COMPAT_DATA_PATH")?);
+    dosdevices.push("pfx");
+    dosdevices.push("dosdevices");
+
+    let mut drives = fs::read_dir(dosdevices).ok()?
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let mut chars = name.to_str()?.chars();
+
+            // Skipping the devices like com1, and the raw devices like z::
+            let drive = match (chars.next(), chars.next(), chars.next()) {
+                (Some(drive), Some(':'), None) if drive.is_ascii_alphabetic() => drive.to_ascii_uppercase(),
+                _ => return None
+            };
+
+            let target = fs::read_link(entry.path()).ok()?;
+            if target.as_os_str() == "/" {
+                Some(drive)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    drives.sort();
+
+    if drives.contains(&'Z') {
+        Some('Z')
+    } else {
+        drives.first().copied()
+    }
+}
+
+/// This folder is ~/.config/Datalink/[gameid]  
+/// Used for storing the per game configs (env variables, udp relay)
+///
+/// If it doesn't exist we create it, if all that fails (or the gameid is no valid folder name) None is returned
+pub fn get_game_config_folder(gameid: &str) -> Option<PathBuf> {
+    if !is_valid_folder_name(gameid) {
+        println!("Datalink: '{gameid}' can not be used as a folder name");
+        return None;
+    }
+
+    let mut folder = get_config_folder()?;
+
+    folder.push(gameid);
+    if !folder.exists() {
+        fs::create_dir(folder.as_path()).ok()?;
+    } else if !folder.is_dir() {
+        return None;
+    }
+
+    Some(folder)
+}
+
+/// Game ids come from the bridge (and configs within the prefix), so they must not be able to
+/// point outside of the folder they are put into
+fn is_valid_folder_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', '\0'])
+}
+
 fn place_bridge_exe(user_debug: bool) -> Option<String> {
     let mut path = get_cache_folder()?;
 
@@ -0,0 +1,60 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use crate::{is_valid_folder_name, udp_relay::{parse_addr, read_relay_file}};
+
+fn write_relay_file(name: &str, content: &str) -> PathBuf {
+    let file = std::env::temp_dir().join(format!("datalink-relay-test-{name}-{}", std::process::id()));
+    std::fs::write(file.as_path(), content).expect("Failed to write relay file");
+    file
+}
+
+#[test]
+pub fn relay_parse_addr() {
+    assert_eq!(parse_addr("20777"), Ok(SocketAddr::from(([127, 0, 0, 1], 20777))));
+    assert_eq!(parse_addr("0.0.0.0:20777"), Ok(SocketAddr::from(([0, 0, 0, 0], 20777))));
+    assert_eq!(parse_addr("[::1]:20778").map(|addr| addr.ip().is_loopback()), Ok(true));
+
+    assert!(parse_addr("65536").is_err());
+    assert!(parse_addr("localhost:20777").is_err());
+    assert!(parse_addr("").is_err());
+}
+
+#[test]
+pub fn relay_read_file() {
+    let file = write_relay_file("valid", "# The game\nlisten=20777\n\nforward=20778\nforward = 192.168.1.20:20777\n");
+    let res = read_relay_file(file.clone());
+    let _ = std::fs::remove_file(file);
+
+    let config = res.expect("Failed to read relay file");
+    assert_eq!(config.listen, SocketAddr::from(([127, 0, 0, 1], 20777)));
+    assert_eq!(config.forward, vec![SocketAddr::from(([127, 0, 0, 1], 20778)), SocketAddr::from(([192, 168, 1, 20], 20777))]);
+
+    let invalid = [
+        ("nolisten", "forward=20778"),
+        ("noforward", "listen=20777"),
+        ("twolisten", "listen=20777\nlisten=20778\nforward=20779"),
+        ("self", "listen=0.0.0.0:20777\nforward=20777"),
+        ("unknown", "listen=20777\nforward=20778\nport=20779"),
+        ("addr", "listen=20777\nforward=nowhere")
+    ];
+    for (name, content) in invalid {
+        let file = write_relay_file(name, content);
+        let res = read_relay_file(file.clone());
+        let _ = std::fs::remove_file(file);
+
+        assert!(res.is_err(), "{name} should not be a valid relay config");
+    }
+
+    assert!(read_relay_file(std::env::temp_dir().join("datalink-relay-test-missing")).is_err());
+}
+
+#[test]
+pub fn game_folder_names() {
+    for name in ["365960", "assettocorsa", "AMS2 (also known as 1066890)"] {
+        assert!(is_valid_folder_name(name), "{name} should be a valid folder name");
+    }
+
+    for name in ["", ".", "..", "../policy.json", "a/b", "..\\..\\.config", "a\0b"] {
+        assert!(!is_valid_folder_name(name), "{name} should not be a valid folder name");
+    }
+}
@@ -0,0 +1,130 @@
+//! Some games only send their UDP telemetry to a single port, and only one programm can bind it.
+//! This relay binds that port for the duration of the game session, and forwards every datagram
+//! to a list of other local ports.
+//!
+//! Configured via ~/.config/Datalink/[gameid]/udp_relay, using the same syntax as the env file:
+//! ```text
+//! listen=20777
+//! forward=20778
+//! forward=127.0.0.1:20779
+//! ```
+//! A bare port is expanded to 127.0.0.1:port, set 0.0.0.0:port to listen for other machines as well.
+
+use std::{fs, net::{SocketAddr, UdpSocket}, path::{Path, PathBuf}, str::FromStr, thread::JoinHandle, time::Duration};
+
+use crate::env_handler::read_env_file;
+
+/// How often the relay checks if the session is still running
+const SESSION_CHECK_RATE: Duration = Duration::from_millis(500);
+
+/// Largest possible UDP payload
+const BUFFER_SIZE: usize = 65535;
+
+pub(crate) struct RelayConfig {
+    pub listen: SocketAddr,
+    pub forward: Vec<SocketAddr>
+}
+
+/// Starts the relay for this game (if configured), which runs until the running file is deleted
+/// or the process named in it exited, so it shares the lifetime with the game session.
+///
+/// Returns None if no relay is configured for this game.
+pub fn start_relay(gameid: &str, running_file: PathBuf) -> Result<Option<JoinHandle<()>>, String> {
+    let file = get_relay_filepath(gameid).ok_or(format!("Failed to create/open ~/.config/Datalink/{} folder", gameid))?;
+
+    if !file.exists() {
+        return Ok(None);
+    }
+
+    let config = read_relay_file(file)?;
+
+    let socket = UdpSocket::bind(config.listen).map_err(|e| format!("Failed to bind udp relay to {}: {}", config.listen, e))?;
+    socket.set_read_timeout(Some(SESSION_CHECK_RATE)).map_err(|e| e.to_string())?;
+
+    println!("Datalink relaying udp from {} to {} port(s) for game {}", config.listen, config.forward.len(), gameid);
+
+    let handle = std::thread::spawn(move || {
+        let mut buffer = vec![0_u8; BUFFER_SIZE];
+
+        while is_session_running(running_file.as_path()) {
+            match socket.recv_from(&mut buffer) {
+                Ok((len, _)) => {
+                    for target in config.forward.iter() {
+                        // A tool not listening (yet) is not an error worth stopping for
+                        let _ = socket.send_to(&buffer[..len], target);
+                    }
+                },
+                Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => (),
+                Err(e) => {
+                    // On linux the icmp port unreachable of a previous send can pop up here
+                    if e.kind() != std::io::ErrorKind::ConnectionRefused {
+                        println!("Datalink udp relay failed: {}", e);
+                        return;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(Some(handle))
+}
+
+/// The session runs as long as the running file exists, and the process it names is alive.
+///
+/// If the bridge (or the game) dies the file is never deleted, but the process is gone, so the
+/// relay does not keep holding the port
+fn is_session_running(running_file: &Path) -> bool {
+    match fs::read_to_string(running_file) {
+        Ok(pid) => match pid.trim().parse::<u32>() {
+            Ok(pid) => Path::new("/proc").join(pid.to_string()).exists(),
+            // Can't tell, so we go by the file alone
+            Err(_) => true
+        },
+        Err(_) => false
+    }
+}
+
+fn get_relay_filepath(gameid: &str) -> Option<PathBuf> {
+    let mut buff = crate::get_game_config_folder(gameid)?;
+    buff.push("udp_relay");
+    Some(buff)
+}
+
+pub(crate) fn read_relay_file(file: PathBuf) -> Result<RelayConfig, String> {
+    let mut listen = None;
+    let mut forward = Vec::<SocketAddr>::new();
+
+    for (key, value) in read_env_file(file)? {
+        match key.as_str() {
+            "listen" => {
+                if listen.is_some() {
+                    return Err("Only one listen port can be set for the udp relay".to_string());
+                }
+                listen = Some(parse_addr(value.as_str())?);
+            },
+            "forward" => forward.push(parse_addr(value.as_str())?),
+            _ => return Err(format!("Unknown key in udp relay config: {}", key))
+        }
+    }
+
+    let listen = listen.ok_or("No listen port set in udp relay config".to_string())?;
+
+    if forward.is_empty() {
+        return Err("No forward ports set in udp relay config".to_string());
+    }
+
+    if forward.iter().any(|f| f.port() == listen.port() && (f.ip().is_loopback() || f.ip() == listen.ip())) {
+        return Err(format!("Udp relay would forward to it's own listen port {}", listen.port()));
+    }
+
+    Ok(RelayConfig { listen, forward })
+}
+
+/// Takes either an ip:port or just a port, which is on localhost
+pub(crate) fn parse_addr(value: &str) -> Result<SocketAddr, String> {
+    if let Ok(port) = u16::from_str(value) {
+        return Ok(SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+
+    SocketAddr::from_str(value).map_err(|e| format!("Unable to parse address {}: {}", value, e))
+}
@@ -1,4 +1,4 @@
-use datalink_bridge_config::{GameBridgeConfig, MemMapConfig};
+use datalink_bridge_config::{install::ToolVersion, GameBridgeConfig, MemMapConfig};
 
 // Requires feature proton to be enabled, you can use this command:
 // cargo run --all-features --example create_acc_config
@@ -10,11 +10,16 @@ fn main() {
     };
 
     let config = GameBridgeConfig::default().with_memory_maps(vec![
-        MemMapConfig { name: "acpmf_crewchief".to_string(), size: 15660 },
-        MemMapConfig { name: "acpmf_static".to_string(), size: 2048 },
-        MemMapConfig { name: "acpmf_physics".to_string(), size: 2048 },
-        MemMapConfig { name: "acpmf_graphics".to_string(), size: 2048 },
+        MemMapConfig { name: "acpmf_crewchief".to_string(), size: 15660, event: None },
+        MemMapConfig { name: "acpmf_static".to_string(), size: 2048, event: None },
+        MemMapConfig { name: "acpmf_physics".to_string(), size: 2048, event: None },
+        MemMapConfig { name: "acpmf_graphics".to_string(), size: 2048, event: None },
     ]);
 
-    let _ = dbg!( config.write_config(&drive, "com.github.lukaslichten.datalink.test", true) ); // LSP doesn't know we have the feature enabled
+    // Only writes the config if it is missing or older, keeping what the user added to it
+    let version = ToolVersion::new(0, 1, 0);
+    match config.ensure_config(&drive, "com.github.lukaslichten.datalink.test", version) { // LSP doesn't know we have the feature enabled
+        Ok(outcome) => println!("{outcome}"),
+        Err(e) => println!("Failed to install config: {e}")
+    }
 }
@@ -0,0 +1,6 @@
+// Requires feature schema to be enabled, regenerates the published schema with:
+// cargo run --features schema --example generate_schema > schema/datalink-config.schema.json
+fn main() {
+    let schema = datalink_bridge_config::schema::json_schema();
+    println!("{}", serde_json::to_string_pretty(&schema).expect("Schema is valid json"));
+}
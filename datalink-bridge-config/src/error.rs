@@ -0,0 +1,130 @@
+//! Errors for reading and writing configs.
+
+use std::{fmt::Display, io, path::{Path, PathBuf}};
+
+/// Error reading or writing a config (or the folder containing them)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// The config folder could not be found
+    NoFolder,
+    /// The file already exists, and overwrite was not set
+    AlreadyExists { path: PathBuf },
+    /// Reading or writing the file (or folder) failed
+    Io { path: PathBuf, kind: io::ErrorKind, message: String },
+    /// The file is not a valid config, line and column as reported by serde (starting at 1, 0 if unknown)
+    Parse { path: PathBuf, line: usize, column: usize, message: String },
+    /// The config could not be turned into json (or toml)
+    Serialize { path: PathBuf, message: String },
+    /// Another tool held the lock on the folder for too long
+    Locked { path: PathBuf },
+    /// Two configs set different values for a setting only one can have, or settings that don't work together
+    Conflict { message: String },
+    /// The config could not be migrated to the current schema version
+    Schema { path: PathBuf, message: String },
+    /// Warning: the config was written for a newer schema version, it is still read
+    NewerSchema { path: PathBuf, version: u32 },
+    /// Warning: the config contains a field that does not exist (like a typo), which is ignored
+    UnknownField { path: PathBuf, field: String }
+}
+
+impl ConfigError {
+    pub(crate) fn io(path: &Path, e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            return Self::AlreadyExists { path: path.to_path_buf() };
+        }
+        Self::Io { path: path.to_path_buf(), kind: e.kind(), message: e.to_string() }
+    }
+
+    pub(crate) fn parse(path: &Path, e: serde_json::Error) -> Self {
+        Self::Parse { path: path.to_path_buf(), line: e.line(), column: e.column(), message: e.to_string() }
+    }
+
+    pub(crate) fn parse_toml(path: &Path, text: &str, e: toml::de::Error) -> Self {
+        let (line, column) = e.span().map(|span| crate::format::line_column(text, span.start)).unwrap_or((0, 0));
+        Self::Parse { path: path.to_path_buf(), line, column, message: e.message().trim_end().to_string() }
+    }
+
+    /// The file (or folder) this error is about, if any
+    pub fn get_path(&self) -> Option<&Path> {
+        match self {
+            Self::NoFolder | Self::Conflict { .. } => None,
+            Self::AlreadyExists { path } | Self::Io { path, .. } | Self::Parse { path, .. } |
+            Self::Serialize { path, .. } | Self::Locked { path } | Self::Schema { path, .. } |
+            Self::NewerSchema { path, .. } | Self::UnknownField { path, .. } => Some(path.as_path())
+        }
+    }
+
+    /// If this only warns about the config, which is still used
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::NewerSchema { .. } | Self::UnknownField { .. })
+    }
+
+    /// The io error kind, `AlreadyExists` and `NotFound` included, None for errors not caused by io
+    pub fn get_io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            Self::AlreadyExists { .. } => Some(io::ErrorKind::AlreadyExists),
+            Self::Io { kind, .. } => Some(*kind),
+            _ => None
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoFolder => write!(f, "Failed to get the config folder path"),
+            Self::AlreadyExists { path } => write!(f, "{} already exists", path.display()),
+            Self::Io { path, message, .. } => write!(f, "Failed to access {}: {message}", path.display()),
+            Self::Parse { path, message, .. } => write!(f, "Failed to parse {}: {message}", path.display()),
+            Self::Serialize { path, message } => write!(f, "Failed to serialize config for {}: {message}", path.display()),
+            Self::Locked { path } => write!(f, "Timed out waiting for the lock on {}", path.display()),
+            Self::Conflict { message } => write!(f, "{message}"),
+            Self::Schema { path, message } => write!(f, "Failed to migrate {}: {message}", path.display()),
+            Self::NewerSchema { path, version } => write!(f, "{} was written for schema version {version}, newer than the supported {}", path.display(), crate::schema::SCHEMA_VERSION),
+            Self::UnknownField { path, field } => write!(f, "Unknown field {field} in {} (ignored)", path.display())
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<ConfigError> for String {
+    fn from(value: ConfigError) -> Self {
+        value.to_string()
+    }
+}
+
+/// All errors that occured while reading a folder of configs, in the order they occured
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl ConfigErrors {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, error: ConfigError) {
+        self.0.push(error);
+    }
+}
+
+impl Display for ConfigErrors {
+    /// One error per line
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, error) in self.0.iter().enumerate() {
+            if index != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+impl From<ConfigError> for ConfigErrors {
+    fn from(value: ConfigError) -> Self {
+        Self(vec![value])
+    }
+}
@@ -0,0 +1,131 @@
+//! File formats of configs: JSON (also with comments and trailing commas) and TOML.
+//!
+//! Both are read into the same `GameBridgeConfig`, the format is picked through the file extension.
+
+use std::path::Path;
+
+use crate::GameBridgeConfig;
+
+/// Format of a config file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// `.json` and `.jsonc`, comments (`//` and `/* */`) and trailing commas are allowed when reading
+    #[default]
+    Json,
+    /// `.toml`
+    Toml
+}
+
+impl ConfigFormat {
+    /// The format for the extension of this path, None if it is not a config
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "json" | "jsonc" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            _ => None
+        }
+    }
+
+    /// Extension used when writing configs in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Toml => "toml"
+        }
+    }
+
+    /// Turns the config into text of this format (json without comments)
+    pub fn serialize(&self, config: &GameBridgeConfig) -> Result<String, String> {
+        match self {
+            Self::Json => serde_json::to_string_pretty(config).map_err(|e| e.to_string()),
+            Self::Toml => toml::to_string_pretty(config).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Replaces comments and trailing commas with spaces, so the text is plain json.
+///
+/// Everything else (including line breaks) stays where it is, so errors point at the right position
+pub(crate) fn strip_jsonc(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut pending_comma: Option<usize> = None;
+
+    // Keeps the byte offsets, line breaks are kept so lines stay the same
+    fn blank(out: &mut String, c: char) {
+        if c == '\n' || c == '\r' {
+            out.push(c);
+        } else {
+            out.extend(std::iter::repeat_n(' ', c.len_utf8()));
+        }
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                pending_comma = None;
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                out.push(escaped);
+                            }
+                        },
+                        '"' => break,
+                        _ => ()
+                    }
+                }
+            },
+            '/' if chars.peek() == Some(&'/') => {
+                blank(&mut out, c);
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    blank(&mut out, c);
+                    chars.next();
+                }
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                blank(&mut out, c);
+                blank(&mut out, chars.next().expect("We peeked it"));
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    blank(&mut out, c);
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            },
+            ',' => {
+                pending_comma = Some(out.len());
+                out.push(c);
+            },
+            '}' | ']' => {
+                if let Some(pos) = pending_comma.take() {
+                    out.replace_range(pos..pos + 1, " ");
+                }
+                out.push(c);
+            },
+            c if c.is_whitespace() => out.push(c),
+            c => {
+                pending_comma = None;
+                out.push(c);
+            }
+        }
+    }
+
+    out
+}
+
+/// Line and column (starting at 1) of the byte offset in the text
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = text.get(..offset).unwrap_or(text);
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|p| p + 1).unwrap_or(0) + 1;
+    (line, column)
+}
@@ -0,0 +1,124 @@
+//! Installing and updating the config a tool ships, without overwriting what the user changed.
+//!
+//! The config is stamped with the version of the tool. It is only replaced by a newer version,
+//! keeping the `user` section (and `enabled`) of the installed config.
+
+use std::{fmt::Display, fs, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::ConfigError, lock::FolderLock, GameBridgeConfig};
+
+/// Version of the tool that installed a config, `major.minor.patch` (missing parts are 0)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ToolVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32
+}
+
+impl ToolVersion {
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+}
+
+impl FromStr for ToolVersion {
+    type Err = String;
+
+    /// Parses `1`, `1.2` or `1.2.3`, optionally with a leading `v`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let trimmed = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+
+        let mut parts = [0; 3];
+        for (index, part) in trimmed.split('.').enumerate() {
+            let slot = parts.get_mut(index).ok_or(format!("Version '{s}' has more than 3 parts"))?;
+            *slot = part.parse().map_err(|_| format!("Invalid version '{s}'"))?;
+        }
+
+        Ok(Self::new(parts[0], parts[1], parts[2]))
+    }
+}
+
+impl TryFrom<String> for ToolVersion {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ToolVersion> for String {
+    fn from(value: ToolVersion) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for ToolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// What `ensure_config` did
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnsureOutcome {
+    /// There was no config, so it was written
+    Created,
+    /// The installed config was older (or had no version), and was replaced.
+    /// The user section of the old config was kept
+    Updated { from: Option<ToolVersion> },
+    /// The same version is installed, nothing was written
+    UpToDate,
+    /// A newer version is installed (by a newer version of the tool), nothing was written
+    NewerInstalled { installed: ToolVersion }
+}
+
+impl Display for EnsureOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Created => write!(f, "Created the config"),
+            Self::Updated { from: Some(from) } => write!(f, "Updated the config from version {from}"),
+            Self::Updated { from: None } => write!(f, "Updated the config (it had no version)"),
+            Self::UpToDate => write!(f, "Config is up to date"),
+            Self::NewerInstalled { installed } => write!(f, "Kept the config, the installed version {installed} is newer")
+        }
+    }
+}
+
+/// Installs the config at this path, or updates it if an older version is installed.
+///
+/// The config is stamped with `version`. An installed config is only replaced if its version is
+/// older (or missing), its `user` section and `enabled` flag are carried over. A config that can't
+/// be read is not overwritten (the error is returned), so a hand edited config with a typo is not lost.
+///
+/// Everything happens while holding the lock on the folder, so is safe to call on every start of the tool
+pub fn manual_ensure_config(path: &Path, config: GameBridgeConfig, version: ToolVersion) -> Result<EnsureOutcome, ConfigError> {
+    let folder = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if !folder.exists() {
+        fs::create_dir_all(folder).map_err(|e| ConfigError::io(folder, e))?;
+    }
+    let lock = FolderLock::acquire(folder)?;
+
+    let mut config = config.with_tool_version(version);
+    if !path.exists() {
+        lock.write_config(path, &config, false)?;
+        return Ok(EnsureOutcome::Created);
+    }
+
+    let installed = crate::manual_read_config(path)?;
+    let from = installed.get_tool_version();
+    match from {
+        Some(installed) if installed > version => return Ok(EnsureOutcome::NewerInstalled { installed }),
+        Some(installed) if installed == version => return Ok(EnsureOutcome::UpToDate),
+        _ => ()
+    }
+
+    config.user = installed.user;
+    config.enabled = installed.enabled;
+    lock.write_config(path, &config, true)?;
+
+    Ok(EnsureOutcome::Updated { from })
+}
@@ -1,27 +1,56 @@
-use std::{fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::Path};
+#[cfg(any(target_os = "windows", feature = "proton"))]
+use std::path::PathBuf;
 
-#[cfg(target_os = "windows")]
 use std::str::FromStr;
 
 use serde::{Deserialize, Deserializer, Serialize};
 
+use error::{ConfigError, ConfigErrors};
+
+pub mod error;
+pub mod format;
+pub mod install;
+pub mod lock;
+pub mod overlay;
+pub mod patch;
+pub mod path;
+pub mod policy;
+pub mod presets;
+pub mod registry;
+pub mod report;
+pub mod schema;
+pub mod template;
+
 #[cfg(test)]
 mod test;
 
 /// Serves only to trick serde
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(description = "Drive letter the linux root is mounted as, Z if not set"))]
 #[serde(transparent)]
 struct DriveLetterWrapper(char);
 
-impl Default for DriveLetterWrapper {
+/// Where wine mounts the linux root, unless the prefix was changed
+const DEFAULT_ROOT_MOUNT_POINT: char = 'Z';
+
+/// Same trick, for a bool that defaults to true
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(description = "Disabled configs are ignored by the bridge, true if not set"))]
+#[serde(transparent)]
+struct EnabledWrapper(bool);
+
+impl Default for EnabledWrapper {
     fn default() -> Self {
-        Self('Z')
+        Self(true)
     }
 }
 
-impl DriveLetterWrapper {
+impl EnabledWrapper {
     fn is_default(&self) -> bool {
-        self.0.eq_ignore_ascii_case(&'z')
+        self.0
     }
 }
 
@@ -31,6 +60,8 @@ impl DriveLetterWrapper {
 ///
 /// So to maintain this being an optional field I have to do this workaround
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schema", schemars(description = "Free text for the tool that wrote the config, like its version"))]
 #[serde(transparent)]
 struct NotesWrapper {
     #[serde(deserialize_with = "never_fail_notes")]
@@ -42,7 +73,12 @@ struct NotesWrapper {
 /// The game_id is usually read from the AppID in the steam launch command,
 /// but can be overwritten here.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GameBridgeConfig {
+    // Configs without it were written before versioning, and are migrated (see schema)
+    #[serde(default)]
+    schema_version: u32,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub game_id: Option<String>,
 
@@ -55,14 +91,43 @@ pub struct GameBridgeConfig {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub post_apps: Vec<AppContainer>,
 
+    #[serde(default, skip_serializing_if = "ProcessTracking::is_default")]
+    pub process_tracking: ProcessTracking,
+
+    #[serde(default, skip_serializing_if = "StartTrigger::is_default")]
+    pub start_trigger: StartTrigger,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overlays: Vec<Overlay>,
+
     // Useful to the individual programm to store the version for example
     // Allows them to update the version number if needed
     #[serde(skip_serializing_if = "Option::is_none")]
     notes: Option<NotesWrapper>, 
 
     // Serves also to prevent manual instanciation, and breakage cause by it and new options
-    #[serde(default, skip_serializing_if = "DriveLetterWrapper::is_default")]
-    root_mount_point: DriveLetterWrapper
+    // None if not set, so even setting Z explicitly overrides the detected drive
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root_mount_point: Option<DriveLetterWrapper>,
+
+    // Configs are merged from the highest priority down, then by file name
+    #[serde(default, skip_serializing_if = "is_zero")]
+    priority: i32,
+
+    #[serde(default, skip_serializing_if = "EnabledWrapper::is_default")]
+    enabled: EnabledWrapper,
+
+    #[serde(default, skip_serializing_if = "ConfigRemovals::is_empty")]
+    remove: ConfigRemovals,
+
+    // Set by install::manual_ensure_config, to only update configs of older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    tool_version: Option<install::ToolVersion>,
+
+    // Left alone when the tool updates the config
+    #[serde(default, skip_serializing_if = "UserEdits::is_empty")]
+    user: UserEdits
 }
 
 /// Insures that even if notes is not properly serialized, that the struct does not fail
@@ -82,7 +147,7 @@ where
 
 impl Default for GameBridgeConfig {
     fn default() -> Self {
-        Self { game_id: None, maps: Vec::default(), apps: Vec::default(), root_mount_point: DriveLetterWrapper::default(), post_apps: Vec::default(), notes: None }
+        Self { schema_version: schema::SCHEMA_VERSION, game_id: None, maps: Vec::default(), apps: Vec::default(), root_mount_point: None, post_apps: Vec::default(), process_tracking: ProcessTracking::default(), start_trigger: StartTrigger::default(), overlays: Vec::default(), notes: None, priority: 0, enabled: EnabledWrapper::default(), remove: ConfigRemovals::default(), tool_version: None, user: UserEdits::default() }
     }
 }
 
@@ -98,8 +163,13 @@ impl GameBridgeConfig {
         let mut index = 0;
 
         while let Some(item) = self.maps.get(index) {
-            if cached_names.contains(&item.name) {
-                self.maps.remove(index);
+            if let Some(pos) = cached_names.iter().position(|n| n == &item.name) {
+                let removed = self.maps.remove(index);
+
+                // The smaller map might be the one that knows about the data event
+                if self.maps[pos].event.is_none() {
+                    self.maps[pos].event = removed.event;
+                }
             } else {
                 cached_names.push(item.name.clone());
                 index += 1;
@@ -135,6 +205,20 @@ impl GameBridgeConfig {
                 index += 1;
             }
         }
+
+        self.process_tracking.sanitize();
+
+        // Only one overlay per target, the first one wins
+        cached_names.clear();
+        self.overlays.retain(|item| {
+            let target = item.target.to_lowercase();
+            if cached_names.contains(&target) {
+                false
+            } else {
+                cached_names.push(target);
+                true
+            }
+        });
     }
 
 
@@ -169,14 +253,113 @@ impl GameBridgeConfig {
     /// The default wine mountpoint for the linux root is Z, but in case this was changed you can
     /// override it with this function
     pub fn with_override_root_mountpoint(mut self, drive_letter: char) -> Self {
-        self.root_mount_point = DriveLetterWrapper(drive_letter);
+        self.root_mount_point = Some(DriveLetterWrapper(drive_letter));
         self
     }
 
     /// Returns the drive letter under which (according to this config) the root of the linux
     /// filesystem is mounted (usually this is Z)
     pub fn get_root_mount_point(&self) -> char {
-        self.root_mount_point.0
+        self.root_mount_point.map(|drive| drive.0).unwrap_or(DEFAULT_ROOT_MOUNT_POINT)
+    }
+
+    /// Uses the drive letter Datalink detected for the linux root (see `get_detected_root_mount_point`),
+    /// unless this config overrides it (even with Z).
+    ///
+    /// Returns a warning if the override does not match the detected drive
+    pub fn apply_detected_root_mount_point(&mut self, detected: char) -> Option<String> {
+        match self.root_mount_point {
+            None => {
+                self.root_mount_point = Some(DriveLetterWrapper(detected.to_ascii_uppercase()));
+                None
+            },
+            Some(drive) if !drive.0.eq_ignore_ascii_case(&detected) => {
+                Some(format!("Config sets the root mountpoint to {}, but the prefix has the linux root mounted as {}, using {}", drive.0, detected.to_ascii_uppercase(), drive.0))
+            },
+            Some(_) => None
+        }
+    }
+
+    /// The version of the config format this config was written for (after reading it is always
+    /// migrated to `schema::SCHEMA_VERSION`)
+    pub fn get_schema_version(&self) -> u32 {
+        self.schema_version
+    }
+
+    /// Sets the priority of this config, configs are merged from the highest priority down (then
+    /// sorted by file name), so on conflicts (like the root_mount_point) the higher priority wins.
+    /// The default is 0
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn get_priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Disabled configs are ignored by the bridge (without having to rename or delete them)
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = EnabledWrapper(enabled);
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.0
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = EnabledWrapper(enabled);
+    }
+
+    /// Sets the entries this config removes from configs with a lower priority
+    pub fn with_removals(mut self, remove: ConfigRemovals) -> Self {
+        self.remove = remove;
+        self
+    }
+
+    pub fn get_removals(&self) -> &ConfigRemovals {
+        &self.remove
+    }
+
+    /// Sets the version of the tool that wrote this config (see `install::manual_ensure_config`)
+    pub fn with_tool_version(mut self, version: install::ToolVersion) -> Self {
+        self.tool_version = Some(version);
+        self
+    }
+
+    pub fn get_tool_version(&self) -> Option<install::ToolVersion> {
+        self.tool_version
+    }
+
+    /// Sets the user section, which tools keep when they update the config
+    pub fn with_user_edits(mut self, user: UserEdits) -> Self {
+        self.user = user;
+        self
+    }
+
+    pub fn get_user_edits(&self) -> &UserEdits {
+        &self.user
+    }
+
+    pub fn set_user_edits(&mut self, user: UserEdits) {
+        self.user = user;
+    }
+
+    /// Moves the entries of the user section into the config, after removing what the user
+    /// removed from the entries of the tool. Returns what was removed
+    ///
+    /// Done by the bridge when reading the configs
+    pub fn apply_user_edits(&mut self) -> Vec<String> {
+        let mut user = std::mem::take(&mut self.user);
+        let removed = user.remove.apply(self);
+
+        self.maps.append(&mut user.maps);
+        self.apps.append(&mut user.apps);
+        self.post_apps.append(&mut user.post_apps);
+        self.overlays.append(&mut user.overlays);
+
+        removed
     }
 
     /// This adds Apps/Commands to run after the game exited and the apps closed.
@@ -186,6 +369,43 @@ impl GameBridgeConfig {
         self
     }
 
+    /// Sets the rules used to find the game process(es) after the launched process exited
+    pub fn with_process_tracking(mut self, tracking: ProcessTracking) -> Self {
+        self.process_tracking = tracking;
+        self
+    }
+
+    /// Sets when the game is considered started (and `StartedPlaying` is sent)
+    pub fn with_start_trigger(mut self, trigger: StartTrigger) -> Self {
+        self.start_trigger = trigger;
+        self
+    }
+
+    /// Sets the files swapped in for the duration of the session
+    pub fn with_overlays(mut self, overlays: Vec<Overlay>) -> Self {
+        self.overlays = overlays;
+        self
+    }
+
+    /// Replaces the placeholders in all apps, actions and overlays.
+    ///
+    /// Unknown placeholders (and env variables that are not set) are kept as they are, and returned
+    /// together with the app, action or overlay they are in
+    pub fn expand_templates(&mut self, vars: &template::TemplateVars) -> Vec<String> {
+        let mut unknown = Vec::new();
+        for item in self.apps.iter_mut().chain(self.post_apps.iter_mut()) {
+            for message in item.expand_templates(vars) {
+                unknown.push(format!("{}: {message}", item.to_string()));
+            }
+        }
+        for overlay in self.overlays.iter_mut() {
+            for message in overlay.expand_templates(vars) {
+                unknown.push(format!("Overlay of {}: {message}", overlay.get_target()));
+            }
+        }
+        unknown
+    }
+
     /// Adds notes to this Config.
     ///
     /// This is useful so you can for example denote the version of this config,
@@ -223,27 +443,39 @@ impl GameBridgeConfig {
     /// Path needs to be an absolute path
     #[cfg(target_os = "windows")]
     pub fn convert_linux_path_to_wine(&self, path: String) -> String {
-        convert_linux_path_to_wine(self.root_mount_point.0, path)
+        convert_linux_path_to_wine(self.get_root_mount_point(), path)
     }
 
-    /// Writes a config with this name into the prefix.
+    /// Writes a config with this name into the prefix (see `manual_write_config`).
     ///
     /// If the config file already exists, and overwrite is false, then it won't be written, and
-    /// false is returned (flase is also returned if any other write error occures)
+    /// `ConfigError::AlreadyExists` is returned
     #[cfg(feature = "proton")]
-    pub fn write_config(self, game_drive: &proton_finder::GameDrive, name: &str, overwrite: bool) -> bool {
-        if let Some(mut path) = get_path_from_prefix(game_drive) {
-            path.push(format!("{name}.json"));
-            manual_write_config(&path, self, overwrite)
-        } else {
-            false
-        }
+    pub fn write_config(self, game_drive: &proton_finder::GameDrive, name: &str, overwrite: bool) -> Result<(), ConfigError> {
+        self.write_config_as(game_drive, name, format::ConfigFormat::Json, overwrite)
+    }
+
+    /// Writes a config with this name into the prefix in this format (the extension is added to the name)
+    #[cfg(feature = "proton")]
+    pub fn write_config_as(self, game_drive: &proton_finder::GameDrive, name: &str, format: format::ConfigFormat, overwrite: bool) -> Result<(), ConfigError> {
+        let mut path = get_path_from_prefix(game_drive).ok_or(ConfigError::NoFolder)?;
+        path.push(format!("{name}.{}", format.extension()));
+        manual_write_config(&path, self, overwrite)
+    }
+
+    /// Installs this config with this name into the prefix, or updates it if an older version is
+    /// installed (see `install::manual_ensure_config`)
+    #[cfg(feature = "proton")]
+    pub fn ensure_config(self, game_drive: &proton_finder::GameDrive, name: &str, version: install::ToolVersion) -> Result<install::EnsureOutcome, ConfigError> {
+        let mut path = get_path_from_prefix(game_drive).ok_or(ConfigError::NoFolder)?;
+        path.push(format!("{name}.json"));
+        install::manual_ensure_config(&path, self, version)
     }
 
     /// Reads the config with this name within this prefix
     #[cfg(feature = "proton")]
-    pub fn read_config(game_drive: &proton_finder::GameDrive, name: &str) -> Result<Self, String> {
-        let mut path = get_path_from_prefix(game_drive).ok_or("Failed to generate file path".to_string())?;
+    pub fn read_config(game_drive: &proton_finder::GameDrive, name: &str) -> Result<Self, ConfigError> {
+        let mut path = get_path_from_prefix(game_drive).ok_or(ConfigError::NoFolder)?;
         path.push(format!("{name}.json"));
         manual_read_config(&path)
     }
@@ -258,15 +490,16 @@ impl GameBridgeConfig {
     /// If one config has the override unset, then `GameBridgeConfig.game_id` will be None, but the
     /// Option<Vec> can still be some and contain the overrides from other configs.
     ///
-    /// If different root_mount_points are set, then the one found first will be used (and a
-    /// message is logged out). The order in which is read is not necessarily alphabetic, and could
-    /// even change run to run.
+    /// Configs are merged from the highest priority down, then sorted by file name. Disabled configs
+    /// are skipped, and removals only apply to configs with a lower priority.
+    /// If different root_mount_points are set, then the one merged first will be used (and a
+    /// message is logged out).
     #[cfg(feature = "proton")]
-    pub fn read_config_for_prefix(game_drive: &proton_finder::GameDrive) -> (Option<(Self, Option<Vec<String>>)>, Result<(), String>) {
+    pub fn read_config_for_prefix(game_drive: &proton_finder::GameDrive) -> (Option<(Self, Option<Vec<String>>)>, Result<(), ConfigErrors>) {
         let path = if let Some(val) = get_path_from_prefix(game_drive) {
             val
         } else {
-            return (None, Err("Failed to get folder path".to_string()));
+            return (None, Err(ConfigError::NoFolder.into()));
         };
         manual_read_configs_from_folder(&path)
     }
@@ -274,31 +507,328 @@ impl GameBridgeConfig {
 
 /// Size and name for an individual memory map
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MemMapConfig {
     pub name: String,
-    pub size: usize
+    pub size: usize,
+
+    /// Name of a Win32 event the game signals when new data was written into this map.  
+    /// The bridge counts these signals into `[name].seq` next to the map in `/dev/shm`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>
 }
 
 impl MemMapConfig {
     /// Creates a new Config for a Memory Map that can hold the struct `T`
     pub fn new<T>(name: String) -> Self where T:Sized {
         let size = std::mem::size_of::<T>();
-        MemMapConfig { name, size }
+        MemMapConfig { name, size, event: None }
+    }
+
+    /// Sets the named event the game signals on fresh data for this map
+    pub fn with_event(mut self, event: String) -> Self {
+        self.event = Some(event);
+        self
+    }
+}
+
+/// Changes the user made to a config installed by a tool, kept when the tool updates the config.
+///
+/// Entries are added to the ones of the config, `remove` removes entries of the config itself
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UserEdits {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maps: Vec<MemMapConfig>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub apps: Vec<AppContainer>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_apps: Vec<AppContainer>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overlays: Vec<Overlay>,
+
+    #[serde(default, skip_serializing_if = "ConfigRemovals::is_empty")]
+    pub remove: ConfigRemovals
+}
+
+impl UserEdits {
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty() && self.apps.is_empty() && self.post_apps.is_empty() && self.overlays.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// Entries a config removes from the configs with a lower priority, like a map of a preset
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ConfigRemovals {
+    /// Names of maps, supporting `*` and `?` wildcards
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maps: Vec<String>,
+
+    /// Exec paths of apps (as written in the config), supporting `*` and `?` wildcards
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub apps: Vec<String>,
+
+    /// Same as `apps`, for the post apps
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_apps: Vec<String>
+}
+
+impl ConfigRemovals {
+    pub fn with_map(mut self, name: String) -> Self {
+        self.maps.push(name);
+        self
+    }
+
+    pub fn with_app(mut self, exec: String) -> Self {
+        self.apps.push(exec);
+        self
+    }
+
+    pub fn with_post_app(mut self, exec: String) -> Self {
+        self.post_apps.push(exec);
+        self
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty() && self.apps.is_empty() && self.post_apps.is_empty()
+    }
+
+    /// Removes the matching entries from the config, returning what was removed
+    pub fn apply(&self, config: &mut GameBridgeConfig) -> Vec<String> {
+        fn matches(patterns: &[String], item: &AppContainer) -> bool {
+            match item {
+                AppContainer::App(app) => patterns.iter().any(|p| wildcard_match(p, app.get_exec())),
+                AppContainer::Action(_) => false
+            }
+        }
+
+        let mut removed = Vec::new();
+        config.maps.retain(|map| {
+            let matched = self.maps.iter().any(|p| wildcard_match(p, map.name.as_str()));
+            if matched {
+                removed.push(format!("map {}", map.name));
+            }
+            !matched
+        });
+        for (kind, patterns, list) in [("app", &self.apps, &mut config.apps), ("post app", &self.post_apps, &mut config.post_apps)] {
+            list.retain(|item| {
+                let matched = matches(patterns, item);
+                if matched {
+                    removed.push(format!("{kind} {}", item.to_string()));
+                }
+                !matched
+            });
+        }
+
+        removed
+    }
+}
+
+/// Defines when the game is considered started, and `StartedPlaying` is sent.
+///
+/// `Launching` is always sent right before the game is launched, so tools can still prepare
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type")]
+pub enum StartTrigger {
+    /// Right before the game is launched
+    #[default]
+    Immediately,
+    /// Once a process matching the `process_tracking` rules is running, which requires executables
+    /// to be set (else the launcher matches as well)
+    Process,
+    /// Once the memory map with this name first contains data (any non zero byte)
+    MapData { map: String }
+}
+
+impl StartTrigger {
+    fn is_default(&self) -> bool {
+        self == &Self::Immediately
+    }
+}
+
+/// Rules for how the bridge finds the game process(es), after the process it launched exited.
+///
+/// By default any process with an exe within the folder (or subfolders) of the game exe is
+/// considered the game.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProcessTracking {
+    /// Names of the executables to track, supporting `*` and `?` wildcards (case insensitive).  
+    /// If empty, every executable in the tracked folders matches
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub executables: Vec<String>,
+
+    /// Further folders to track besides the folder of the game exe.  
+    /// Relative paths are relative to the folder of the game exe
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folders: Vec<String>,
+
+    /// How long (in ms) no matching process has to be found before the game is considered closed,
+    /// so games relaunching themselves are not cut short
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grace_period_ms: Option<u64>,
+
+    /// How often (in ms) the processes are checked when the bridge has to fall back to polling,
+    /// defaults to 5s (and is at least 100ms)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_ms: Option<u64>
+}
+
+impl ProcessTracking {
+    fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Returns if this executable (file name only) matches the executable patterns
+    pub fn matches_executable(&self, exe_name: &str) -> bool {
+        self.executables.is_empty() || self.executables.iter().any(|pattern| wildcard_match(pattern, exe_name))
+    }
+
+    /// Merges the rules of another config into this one.
+    ///
+    /// The longest grace period and shortest poll interval are used
+    pub fn merge(&mut self, mut other: ProcessTracking) {
+        self.executables.append(&mut other.executables);
+        self.folders.append(&mut other.folders);
+
+        self.grace_period_ms = self.grace_period_ms.max(other.grace_period_ms);
+        self.poll_interval_ms = match (self.poll_interval_ms, other.poll_interval_ms) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b)
+        };
+    }
+
+    /// Removes dublicate executables and folders
+    pub fn sanitize(&mut self) {
+        let mut cached = Vec::<String>::with_capacity(self.executables.len());
+        self.executables.retain(|item| {
+            let lower = item.to_lowercase();
+            if cached.contains(&lower) {
+                false
+            } else {
+                cached.push(lower);
+                true
+            }
+        });
+
+        cached.clear();
+        self.folders.retain(|item| {
+            if cached.contains(item) {
+                false
+            } else {
+                cached.push(item.clone());
+                true
+            }
+        });
+    }
+
+    /// Returns the folders to track, the folder of the game exe included
+    #[cfg(target_os = "windows")]
+    pub fn get_folders(&self, drive_letter: char, game_folder: &std::path::Path) -> Vec<PathBuf> {
+        let mut folders = vec![game_folder.to_path_buf()];
+
+        for item in self.folders.iter() {
+            let mut iter = item.chars();
+            let c = iter.next();
+            let next = iter.next();
+
+            let path = if c == Some('/') || (c.is_some_and(|c| c.is_alphabetic()) && next == Some(':')) {
+                convert_path(drive_letter, item.as_str()).and_then(|p| PathBuf::from_str(p.as_str()).ok())
+            } else {
+                // Relative to the game
+                let mut path = game_folder.to_path_buf();
+                for part in item.split(['/', '\\']) {
+                    match part {
+                        "" | "." => (),
+                        ".." => { path.pop(); },
+                        _ => path.push(part)
+                    }
+                }
+                Some(path)
+            };
+
+            if let Some(path) = path {
+                folders.push(path);
+            }
+        }
+
+        folders
+    }
+
+    /// The time no process may be found before the game is considered closed
+    pub fn get_grace_period(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.grace_period_ms.unwrap_or(0))
+    }
+
+    /// The time between process checks, never shorter than 100ms (so a 0 does not busy loop)
+    pub fn get_poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.poll_interval_ms.unwrap_or(DEFAULT_POLL_INTERVAL_MS).max(MIN_POLL_INTERVAL_MS))
+    }
+}
+
+const DEFAULT_POLL_INTERVAL_MS: u64 = 5000;
+const MIN_POLL_INTERVAL_MS: u64 = 100;
+
+/// Matches text against a pattern with `*` (any amount of characters) and `?` (one character)
+/// wildcards, ignoring case (as windows does for file names)
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last star, and the text position it currently covers up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, covered)) = backtrack {
+            // Let the star cover one more character
+            p = star + 1;
+            t = covered + 1;
+            backtrack = Some((star, t));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
+// Only a handful of these exist per config, so boxing the App is not worth it
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum AppContainer {
     App(App),
     Action(Action),
 }
 
+impl AppContainer {
+    /// Replaces the placeholders in the app or action, returns the unknown ones
+    pub fn expand_templates(&mut self, vars: &template::TemplateVars) -> Vec<String> {
+        match self {
+            Self::App(app) => app.expand_templates(vars),
+            Self::Action(action) => action.expand_templates(vars)
+        }
+    }
+}
+
 impl ToString for AppContainer {
     fn to_string(&self) -> String {
         match self {
             Self::App(a) => a.to_string(),
-            Self::Action(Action::Delete { file }) => format!("DELETE {file}")
+            Self::Action(a) => a.get_description()
         }
     }
 }
@@ -317,64 +847,104 @@ impl From<Action> for AppContainer {
 
 #[cfg(target_os = "windows")]
 fn convert_path(drive_letter: char, path: &str) -> Option<String> {
-    let mut iter = path.chars();
-    let c = iter.next()?;
-    let next = iter.next();
-    
-
-    let path = if c.is_alphabetic() && next == Some(':') {
-        // Windows path, already formated
-        path.to_string()
-    } else if c == '/' {
-        // Linux path
-        convert_linux_path_to_wine(drive_letter, path.to_string())
-    } else {
-        // Relative path
-        let rel = path.replace("/", "\\");
-        
-        let mut path = get_config_folder_path()?;
-        path.push(rel);
+    let path = path::ConfigPath::from_str(path).ok()?;
 
-        path.to_str()?.to_string()
+    // Only relative paths need the config folder
+    let config_dir = match path {
+        path::ConfigPath::ConfigRelative(_) => get_config_folder_path()?,
+        _ => PathBuf::new()
     };
 
-    Some(path)
+    path.to_windows(drive_letter, config_dir.as_path())
 }
 
 /// Defines an app to be launched with the game
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct App {
     path: String,
-    args: Vec<String>
+    args: Vec<String>,
+
+    // Start conditions, the app (and all following apps) are launched in the background
+    // once these are met, so the game launch is not delayed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delay_ms: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wait_for_process: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wait_for_file: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wait_for_map_data: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout_ms: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    run_if: Option<RunCondition>,
+
+    #[serde(default, skip_serializing_if = "RestartPolicy::is_default")]
+    restart: RestartPolicy,
+
+    // Process options
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+
+    /// Env variables to set, null removes it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, Option<String>>,
+
+    #[serde(default, skip_serializing_if = "WindowMode::is_default")]
+    window: WindowMode,
+
+    #[serde(default, skip_serializing_if = "ShutdownPolicy::is_default")]
+    shutdown: ShutdownPolicy,
+
+    // Run to completion, the bridge waits for the app to exit successfully before moving on
+    #[serde(default, skip_serializing_if = "is_false")]
+    wait: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wait_timeout_ms: Option<u64>
 }
 
 impl App {
+    fn from_path(path: String) -> Self {
+        Self {
+            path,
+            args: vec![],
+            delay_ms: None,
+            wait_for_process: None,
+            wait_for_file: None,
+            wait_for_map_data: None,
+            timeout_ms: None,
+            run_if: None,
+            restart: RestartPolicy::Never,
+            cwd: None,
+            env: BTreeMap::new(),
+            window: WindowMode::Normal,
+            shutdown: ShutdownPolicy::default(),
+            wait: false,
+            wait_timeout_ms: None
+        }
+    }
 
     /// Creates a new autolaunch app  
     ///
-    /// The path needs to follow the following guidelines:
+    /// The path is checked the same way the bridge converts it (see `path::ConfigPath`):
     /// - linux paths can only start from root (Datalink will auto convert these), with /
-    /// - Windows paths need a drive letter, and singular \
-    /// - Relative paths have to start with a dot, pathing relative to the config location, either
-    /// style of slashes supported
+    /// - Windows paths need a drive letter
+    /// - `%VAR%` at the start is relative to the folder in the env variable
+    /// - A known placeholder at the start (like `{game_dir}`) is accepted, it is only checked once
+    ///   the bridge expanded it
+    /// - Everything else is relative to the config location, either style of slashes supported
     pub fn new(exec: String) -> Option<Self> {
-        let c = exec.chars().next()?;
-
-        if c.is_alphabetic() && !exec.contains('/') && !exec.contains("\\\\") {
-            // Windows
-            let pre = format!("{c}:\\");
-            if exec.strip_prefix(&pre).is_some() {
-                return Some(Self { path: exec, args: vec![] });
-            }
-
-        } else if c == '/' && !exec.contains('\\') {
-            // Linux
-            return Some(Self { path: exec, args: vec![] });
-        } else if c == '.' {
-            return Some(Self { path: exec, args: vec![] });
+        if !template::starts_with_placeholder(exec.as_str()) {
+            path::ConfigPath::from_str(exec.as_str()).ok()?;
         }
-        
-        None
+        Some(Self::from_path(exec))
     }
     
     /// Adds arguments to this App
@@ -383,39 +953,244 @@ impl App {
         self
     }
 
-    /// Returns the exec path set for this App
-    pub fn get_exec<'a>(&'a self) -> &'a str {
-        self.path.as_str()
+    /// Delays the launch of this app (and all following apps) by this many milliseconds
+    pub fn with_delay(mut self, delay_ms: u64) -> Self {
+        self.delay_ms = Some(delay_ms);
+        self
     }
 
-    /// Returns the arguments set for this App
-    pub fn get_args_as_ref<'a>(&'a self) -> &'a Vec<String> {
-        &self.args
+    /// Waits with the launch until a process with this exe name is running.  
+    /// `*` and `?` are supported as wildcards
+    pub fn with_wait_for_process(mut self, exe_name: String) -> Self {
+        self.wait_for_process = Some(exe_name);
+        self
     }
 
-    /// Returns the arguments set for this App, consuming the app
-    pub fn get_args(self) -> Vec<String> {
-        self.args
+    /// Waits with the launch until this file exists, the path is converted the same way as the exec
+    pub fn with_wait_for_file(mut self, file: String) -> Self {
+        self.wait_for_file = Some(file);
+        self
     }
 
-    /// Returns a userfacing name for the application being launched.  
-    ///
-    /// This is usually the exe name
-    pub fn get_name<'a>(&'a self) -> &'a str {
-        let win = if let Some(last) = self.path.split('\\').last() {
-            last
-        } else {
-            self.path.as_str()
-        };
+    /// Waits with the launch until the memory map with this name first contains data
+    pub fn with_wait_for_map_data(mut self, map: String) -> Self {
+        self.wait_for_map_data = Some(map);
+        self
+    }
 
-        let lin = if let Some(last) = self.path.split('/').last() {
-            last
-        } else {
-            self.path.as_str()
-        };
+    /// Sets how long to wait for the start conditions, after which the app is skipped
+    pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
 
-        // Why do we do this?
-        // Because the relative path could technically have both types of symbol, so
+    /// Only launches this app if the condition is met
+    pub fn with_run_if(mut self, condition: RunCondition) -> Self {
+        self.run_if = Some(condition);
+        self
+    }
+
+    /// Sets if (and how often) the app is restarted when it crashes while the game is running
+    pub fn with_restart(mut self, restart: RestartPolicy) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    /// Sets the working directory of the app (converted the same way as the exec path)
+    pub fn with_cwd(mut self, cwd: String) -> Self {
+        self.cwd = Some(cwd);
+        self
+    }
+
+    /// Sets an env variable for the app
+    pub fn with_env(mut self, key: String, value: String) -> Self {
+        self.env.insert(key, Some(value));
+        self
+    }
+
+    /// Removes an env variable (that would be inherited from the game) for the app
+    pub fn with_env_removed(mut self, key: String) -> Self {
+        self.env.insert(key, None);
+        self
+    }
+
+    /// Sets how the window of the app is shown
+    pub fn with_window(mut self, window: WindowMode) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Sets how the app is closed once the game exits
+    pub fn with_shutdown(mut self, shutdown: ShutdownPolicy) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Sets if the bridge waits for the app to finish before moving on
+    pub fn with_wait(mut self, wait: bool) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Sets how long the bridge waits for the app to finish, before killing it
+    pub fn with_wait_timeout(mut self, timeout_ms: u64) -> Self {
+        self.wait_timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Returns the delay before launching
+    pub fn get_delay(&self) -> Option<std::time::Duration> {
+        self.delay_ms.map(std::time::Duration::from_millis)
+    }
+
+    /// Returns the exe name pattern of the process to wait for
+    pub fn get_wait_for_process(&self) -> Option<&str> {
+        self.wait_for_process.as_deref()
+    }
+
+    /// Returns the file to wait for (unconverted)
+    pub fn get_wait_for_file(&self) -> Option<&str> {
+        self.wait_for_file.as_deref()
+    }
+
+    /// Returns the name of the memory map to wait for
+    pub fn get_wait_for_map_data(&self) -> Option<&str> {
+        self.wait_for_map_data.as_deref()
+    }
+
+    /// Returns how long to wait for the start conditions
+    pub fn get_timeout(&self) -> Option<std::time::Duration> {
+        self.timeout_ms.map(std::time::Duration::from_millis)
+    }
+
+    /// Returns the condition for running this app
+    pub fn get_run_if(&self) -> Option<&RunCondition> {
+        self.run_if.as_ref()
+    }
+
+    /// Returns the restart policy of this app
+    pub fn get_restart(&self) -> &RestartPolicy {
+        &self.restart
+    }
+
+    /// Returns the working directory set for this App, without conversion
+    pub fn get_cwd(&self) -> Option<&str> {
+        self.cwd.as_deref()
+    }
+
+    /// Returns the env variables to set (Some) or remove (None)
+    pub fn get_env(&self) -> &BTreeMap<String, Option<String>> {
+        &self.env
+    }
+
+    /// Returns how the window of the app is shown
+    pub fn get_window(&self) -> WindowMode {
+        self.window
+    }
+
+    /// Returns how the app is closed once the game exits
+    pub fn get_shutdown(&self) -> &ShutdownPolicy {
+        &self.shutdown
+    }
+
+    /// If the bridge waits for the app to finish (successfully) before moving on
+    pub fn waits(&self) -> bool {
+        self.wait
+    }
+
+    /// Returns how long the bridge waits for the app to finish (10 minutes if not set)
+    pub fn get_wait_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.wait_timeout_ms.unwrap_or(DEFAULT_WAIT_TIMEOUT_MS))
+    }
+
+    /// Replaces the placeholders in path, args, cwd, env values, wait_for_file and the stop command,
+    /// returns the unknown ones
+    pub fn expand_templates(&mut self, vars: &template::TemplateVars) -> Vec<String> {
+        let mut unknown = Vec::new();
+        vars.expand_in(&mut self.path, &mut unknown);
+        for arg in self.args.iter_mut() {
+            vars.expand_in(arg, &mut unknown);
+        }
+
+        if let Some(cwd) = self.cwd.as_mut() {
+            vars.expand_in(cwd, &mut unknown);
+        }
+        for value in self.env.values_mut().flatten() {
+            vars.expand_in(value, &mut unknown);
+        }
+        if let Some(file) = self.wait_for_file.as_mut() {
+            vars.expand_in(file, &mut unknown);
+        }
+
+        if let Some(stop) = self.shutdown.stop_command.as_mut() {
+            unknown.extend(stop.expand_templates(vars));
+        }
+        unknown
+    }
+
+    /// The exec, cwd and wait_for_file (unconverted), including those of the stop command
+    pub fn get_paths(&self) -> Vec<&str> {
+        let mut paths = vec![self.path.as_str()];
+        paths.extend(self.cwd.as_deref());
+        paths.extend(self.wait_for_file.as_deref());
+
+        if let Some(stop) = self.shutdown.stop_command.as_ref() {
+            paths.extend(stop.get_paths());
+        }
+        paths
+    }
+
+    /// If the launch of this app has to wait for something
+    pub fn has_start_conditions(&self) -> bool {
+        self.delay_ms.is_some() || self.wait_for_process.is_some() || self.wait_for_file.is_some() || self.wait_for_map_data.is_some()
+    }
+
+    /// Checks the run_if condition, returns true if none is set
+    #[cfg(target_os = "windows")]
+    pub fn should_run(&self, drive_letter: char) -> bool {
+        self.run_if.as_ref().is_none_or(|cond| cond.is_met(drive_letter))
+    }
+
+    /// Returns the converted path of the file to wait for
+    #[cfg(target_os = "windows")]
+    pub fn get_wait_for_file_path(&self, drive_letter: char) -> Option<PathBuf> {
+        let path = convert_path(drive_letter, self.wait_for_file.as_ref()?.as_str())?;
+        PathBuf::from_str(path.as_str()).ok()
+    }
+
+    /// Returns the exec path set for this App
+    pub fn get_exec<'a>(&'a self) -> &'a str {
+        self.path.as_str()
+    }
+
+    /// Returns the arguments set for this App
+    pub fn get_args_as_ref<'a>(&'a self) -> &'a Vec<String> {
+        &self.args
+    }
+
+    /// Returns the arguments set for this App, consuming the app
+    pub fn get_args(self) -> Vec<String> {
+        self.args
+    }
+
+    /// Returns a userfacing name for the application being launched.  
+    ///
+    /// This is usually the exe name
+    pub fn get_name<'a>(&'a self) -> &'a str {
+        let win = if let Some(last) = self.path.split('\\').last() {
+            last
+        } else {
+            self.path.as_str()
+        };
+
+        let lin = if let Some(last) = self.path.split('/').last() {
+            last
+        } else {
+            self.path.as_str()
+        };
+
+        // Why do we do this?
+        // Because the relative path could technically have both types of symbol, so
         // we check for the path with the shortest result, excluding empty
 
         match (win.is_empty(), lin.is_empty(), win.len() > lin.len()) {
@@ -434,10 +1209,226 @@ impl App {
         let mut cmd = std::process::Command::new(path);
         cmd.args(self.args);
 
+        if let Some(cwd) = self.cwd.as_ref() {
+            cmd.current_dir(convert_path(drive_letter, cwd.as_str())?);
+        }
+
+        for (key, value) in self.env {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key)
+            };
+        }
+
+        std::os::windows::process::CommandExt::creation_flags(&mut cmd, self.window.get_creation_flags());
+
         Some(cmd)
     }
 }
 
+/// How the window of an app is shown
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum WindowMode {
+    #[default]
+    Normal,
+    /// The windows of the app are minimized once they appear (done by the bridge)
+    Minimized,
+    /// Console apps are launched without a console window
+    NoWindow
+}
+
+impl WindowMode {
+    fn is_default(&self) -> bool {
+        self == &Self::Normal
+    }
+
+    /// Returns the process creation flags for this mode
+    pub fn get_creation_flags(&self) -> u32 {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+        match self {
+            Self::NoWindow => CREATE_NO_WINDOW,
+            Self::Normal | Self::Minimized => 0
+        }
+    }
+}
+
+/// Default time an app gets to close gracefully
+const DEFAULT_SHUTDOWN_TIMEOUT_MS: u64 = 5000;
+
+/// Default time the bridge waits for an app set to wait, so a hanging one does not block it forever
+const DEFAULT_WAIT_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+
+/// Defines how an app is closed once the game exits
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ShutdownPolicy {
+    /// How long the app gets to close, before it is killed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout_ms: Option<u64>,
+
+    /// Closes/kills the child processes of the app too
+    #[serde(default, skip_serializing_if = "is_false")]
+    kill_tree: bool,
+
+    /// Run instead of asking the app to close
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stop_command: Option<Box<App>>,
+
+    /// Leaves the app running after the game exits
+    #[serde(default, skip_serializing_if = "is_false")]
+    detach: bool
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+impl ShutdownPolicy {
+    fn is_default(&self) -> bool {
+        self.timeout_ms.is_none() && !self.kill_tree && self.stop_command.is_none() && !self.detach
+    }
+
+    /// Sets how long the app gets to close gracefully
+    pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Sets if the child processes of the app are closed too
+    pub fn with_kill_tree(mut self, kill_tree: bool) -> Self {
+        self.kill_tree = kill_tree;
+        self
+    }
+
+    /// Sets a command to run to close the app, instead of asking it to close
+    pub fn with_stop_command(mut self, stop_command: App) -> Self {
+        self.stop_command = Some(Box::new(stop_command));
+        self
+    }
+
+    /// Sets if the app is left running after the game exits
+    pub fn with_detach(mut self, detach: bool) -> Self {
+        self.detach = detach;
+        self
+    }
+
+    /// Returns how long the app gets to close gracefully, before it is killed
+    pub fn get_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.timeout_ms.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_MS))
+    }
+
+    /// If the child processes of the app are closed too
+    pub fn kills_tree(&self) -> bool {
+        self.kill_tree
+    }
+
+    /// Returns the command to run to close the app, if set
+    pub fn get_stop_command(&self) -> Option<&App> {
+        self.stop_command.as_deref()
+    }
+
+    /// If the app is left running after the game exits
+    pub fn is_detached(&self) -> bool {
+        self.detach
+    }
+}
+
+/// Defines if an app is restarted when it exits with a failure while the game is running
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type")]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    /// Restarts the app up to `max_retries` times, waiting `backoff_ms` before the first restart,
+    /// doubling it for every further one
+    OnFailure {
+        max_retries: u32,
+        #[serde(default)]
+        backoff_ms: u64
+    }
+}
+
+impl RestartPolicy {
+    fn is_default(&self) -> bool {
+        self == &Self::Never
+    }
+
+    /// Returns how long to wait before restarting, after the app already restarted this many times.
+    ///
+    /// None if the app should not be restarted (anymore)
+    pub fn get_backoff(&self, restarts: u32) -> Option<std::time::Duration> {
+        match self {
+            Self::Never => None,
+            Self::OnFailure { max_retries, backoff_ms } => {
+                if restarts >= *max_retries {
+                    return None;
+                }
+
+                let factor = 1_u64.checked_shl(restarts).unwrap_or(u64::MAX);
+                Some(std::time::Duration::from_millis(backoff_ms.saturating_mul(factor)))
+            }
+        }
+    }
+}
+
+/// Name of the folder (within `AppData\Roaming` of the prefix) the configs are in
+pub const CONFIG_FOLDER_NAME: &str = "Datalink";
+
+/// Name of the env variable Datalink sets (to the override exec) when the game is overriden
+pub const OVERRIDE_ENV_VAR: &str = "DATALINK_OVERRIDE";
+
+/// Name of the env variable Datalink sets to the drive letter the linux root is mounted as
+/// (found through the `dosdevices` of the prefix)
+pub const ROOT_DRIVE_ENV_VAR: &str = "DATALINK_ROOT_DRIVE";
+
+/// The drive letter Datalink detected for the linux root, None if it was not passed on (or invalid)
+pub fn get_detected_root_mount_point() -> Option<char> {
+    let value = std::env::var(ROOT_DRIVE_ENV_VAR).ok()?;
+    let mut chars = value.trim().chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(drive), None) if drive.is_ascii_alphabetic() => Some(drive.to_ascii_uppercase()),
+        _ => None
+    }
+}
+
+/// Condition for running an app/action
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type")]
+pub enum RunCondition {
+    /// The file exists, the path is converted the same way as the exec path of apps
+    FileExists { file: String },
+    /// The env variable is set (to anything)
+    EnvSet { var: String },
+    /// Datalink was launched with `--override`
+    OverrideActive,
+    /// The contained condition is not met
+    Not { condition: Box<RunCondition> }
+}
+
+impl RunCondition {
+    /// Checks if this condition is met
+    #[cfg(target_os = "windows")]
+    pub fn is_met(&self, drive_letter: char) -> bool {
+        match self {
+            Self::FileExists { file } => convert_path(drive_letter, file.as_str())
+                .and_then(|p| PathBuf::from_str(p.as_str()).ok())
+                .is_some_and(|p| p.exists()),
+            Self::EnvSet { var } => std::env::var_os(var).is_some(),
+            Self::OverrideActive => std::env::var_os(OVERRIDE_ENV_VAR).is_some(),
+            Self::Not { condition } => !condition.is_met(drive_letter)
+        }
+    }
+}
+
 impl ToString for App {
     fn to_string(&self) -> String {
         let mut output = self.path.clone();
@@ -450,29 +1441,244 @@ impl ToString for App {
     }
 }
 
+/// File operations performed in order with the apps.
+///
+/// All paths are converted the same way as the exec path of apps. `Delete`, `Copy` and `Move`
+/// support `*` and `?` wildcards in the last part of the path
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(tag = "type")]
 pub enum Action {
-    Delete{ file: String }
+    /// Deletes the file or folder, folders with content only if recursive
+    Delete {
+        file: String,
+        #[serde(default, skip_serializing_if = "is_false")]
+        recursive: bool
+    },
+    /// Copies the file or folder (with content), into `to` if it is an existing folder
+    Copy {
+        from: String,
+        to: String,
+        #[serde(default, skip_serializing_if = "is_false")]
+        overwrite: bool
+    },
+    /// Moves the file or folder, into `to` if it is an existing folder
+    Move {
+        from: String,
+        to: String,
+        #[serde(default, skip_serializing_if = "is_false")]
+        overwrite: bool
+    },
+    /// Creates the folder (and its parents), if it does not exist
+    Mkdir { path: String },
+    /// Creates a symlink at `link` pointing to `target`
+    Symlink { target: String, link: String },
+    /// Sets the values in a json file, keys are JSON pointers (see `patch::patch_json`)
+    PatchJson {
+        file: String,
+        set: BTreeMap<String, serde_json::Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encoding: Option<patch::TextEncoding>
+    },
+    /// Sets the values in an ini file, grouped by section (see `patch::patch_ini`)
+    PatchIni {
+        file: String,
+        set: BTreeMap<String, BTreeMap<String, String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encoding: Option<patch::TextEncoding>
+    },
+    /// Sets element texts and attributes in a xml file (see `patch::patch_xml`)
+    PatchXml {
+        file: String,
+        set: BTreeMap<String, String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        encoding: Option<patch::TextEncoding>
+    },
+    /// Sets a registry value (empty name for the default value), creating the key if needed.
+    ///
+    /// Performed by the bridge, restore reverts it once the game exited
+    RegSet {
+        key: String,
+        #[serde(default)]
+        name: String,
+        #[serde(flatten)]
+        value: RegistryValue,
+        #[serde(default, skip_serializing_if = "is_false")]
+        restore: bool
+    },
+    /// Deletes a registry value, does nothing if it does not exist.
+    ///
+    /// Performed by the bridge, restore recreates it once the game exited
+    RegDelete {
+        key: String,
+        #[serde(default)]
+        name: String,
+        #[serde(default, skip_serializing_if = "is_false")]
+        restore: bool
+    }
 }
 
 impl Action {
+    /// Returns a short description of the action, used for logging and removing duplicates
+    pub fn get_description(&self) -> String {
+        match self {
+            Self::Delete { file, recursive: false } => format!("DELETE {file}"),
+            Self::Delete { file, recursive: true } => format!("DELETE -r {file}"),
+            Self::Copy { from, to, .. } => format!("COPY {from} {to}"),
+            Self::Move { from, to, .. } => format!("MOVE {from} {to}"),
+            Self::Mkdir { path } => format!("MKDIR {path}"),
+            Self::Symlink { target, link } => format!("SYMLINK {target} {link}"),
+            Self::PatchJson { file, set, .. } => format!("PATCH {file} {set:?}"),
+            Self::PatchIni { file, set, .. } => format!("PATCH {file} {set:?}"),
+            Self::PatchXml { file, set, .. } => format!("PATCH {file} {set:?}"),
+            Self::RegSet { key, name, value, .. } => format!("REGSET {key} {name} {value:?}"),
+            Self::RegDelete { key, name, .. } => format!("REGDELETE {key} {name}")
+        }
+    }
+
+    /// Replaces the placeholders in the paths of this action, and in key, name and string data of
+    /// registry actions (`ExpandString` data is left for windows to expand), returns the unknown ones
+    pub fn expand_templates(&mut self, vars: &template::TemplateVars) -> Vec<String> {
+        let mut unknown = Vec::new();
+        match self {
+            Self::Delete { file, .. } | Self::PatchJson { file, .. } | Self::PatchIni { file, .. } | Self::PatchXml { file, .. } => vars.expand_in(file, &mut unknown),
+            Self::Copy { from, to, .. } | Self::Move { from, to, .. } => {
+                vars.expand_in(from, &mut unknown);
+                vars.expand_in(to, &mut unknown);
+            },
+            Self::Mkdir { path } => vars.expand_in(path, &mut unknown),
+            Self::Symlink { target, link } => {
+                vars.expand_in(target, &mut unknown);
+                vars.expand_in(link, &mut unknown);
+            },
+            Self::RegSet { key, name, value, .. } => {
+                vars.expand_in(key, &mut unknown);
+                vars.expand_in(name, &mut unknown);
+                unknown.extend(value.expand_templates(vars));
+            },
+            Self::RegDelete { key, name, .. } => {
+                vars.expand_in(key, &mut unknown);
+                vars.expand_in(name, &mut unknown);
+            }
+        }
+        unknown
+    }
+
+    /// The files and folders this action reads or writes (unconverted), none for registry actions
+    pub fn get_paths(&self) -> Vec<&str> {
+        match self {
+            Self::Delete { file, .. } | Self::PatchJson { file, .. } | Self::PatchIni { file, .. } | Self::PatchXml { file, .. } => vec![file.as_str()],
+            Self::Copy { from, to, .. } | Self::Move { from, to, .. } => vec![from.as_str(), to.as_str()],
+            Self::Mkdir { path } => vec![path.as_str()],
+            Self::Symlink { target, link } => vec![target.as_str(), link.as_str()],
+            Self::RegSet { .. } | Self::RegDelete { .. } => vec![]
+        }
+    }
+
     #[cfg(target_os = "windows")]
     pub fn perform(self, drive_letter: char) -> std::io::Result<()> {
         match self {
-            Self::Delete { file } => {
-                let path = convert_path(drive_letter, file.as_str()).ok_or(std::io::Error::from(std::io::ErrorKind::InvalidData))?;
-
-                let f = PathBuf::from_str(path.as_str()).expect("Baddly formated string, but this is infallible, so it should not fail");
-                if f.is_file() {
-                    println!("Deleting file {}", path.as_str());
-                    fs::remove_file(f)?;
-                } else if f.is_dir() {
-                    println!("Deleting folder {}", path.as_str());
-                    fs::remove_dir(f)?;
-                } else {
-                    println!("No file at '{}', continuing", path.as_str());
+            Self::Delete { file, recursive } => {
+                let path = action_path(drive_letter, file.as_str())?;
+                let matches = expand_wildcards(path.as_path())?;
+
+                if matches.is_empty() {
+                    println!("No file at '{}', continuing", path.display());
+                }
+
+                for f in matches {
+                    if f.is_dir() {
+                        println!("Deleting folder {}", f.display());
+                        if recursive {
+                            fs::remove_dir_all(f.as_path())
+                        } else {
+                            fs::remove_dir(f.as_path())
+                        }.map_err(|e| action_error(e, "Failed to delete folder", f.as_path()))?;
+                    } else {
+                        println!("Deleting file {}", f.display());
+                        fs::remove_file(f.as_path()).map_err(|e| action_error(e, "Failed to delete file", f.as_path()))?;
+                    }
+                }
+            },
+            Self::Copy { from, to, overwrite } => {
+                let (sources, to) = action_source_target(drive_letter, from.as_str(), to.as_str())?;
+
+                for from in sources {
+                    let target = target_path(from.as_path(), to.as_path(), overwrite)?;
+                    println!("Copying {} to {}", from.display(), target.display());
+                    copy_all(from.as_path(), target.as_path())?;
+                }
+            },
+            Self::Move { from, to, overwrite } => {
+                let (sources, to) = action_source_target(drive_letter, from.as_str(), to.as_str())?;
+
+                for from in sources {
+                    let target = target_path(from.as_path(), to.as_path(), overwrite)?;
+                    println!("Moving {} to {}", from.display(), target.display());
+
+                    if target.exists() {
+                        // Checked by target_path that we are allowed to overwrite
+                        if target.is_dir() {
+                            fs::remove_dir_all(target.as_path())
+                        } else {
+                            fs::remove_file(target.as_path())
+                        }.map_err(|e| action_error(e, "Failed to remove existing", target.as_path()))?;
+                    }
+
+                    if fs::rename(from.as_path(), target.as_path()).is_err() {
+                        // Drive letters can be different filesystems on linux, so rename fails
+                        copy_all(from.as_path(), target.as_path())?;
+                        if from.is_dir() {
+                            fs::remove_dir_all(from.as_path())
+                        } else {
+                            fs::remove_file(from.as_path())
+                        }.map_err(|e| action_error(e, "Copied, but failed to remove", from.as_path()))?;
+                    }
                 }
+            },
+            Self::Mkdir { path } => {
+                let path = action_path(drive_letter, path.as_str())?;
+
+                if !path.is_dir() {
+                    println!("Creating folder {}", path.display());
+                    fs::create_dir_all(path.as_path()).map_err(|e| action_error(e, "Failed to create folder", path.as_path()))?;
+                }
+            },
+            Self::Symlink { target, link } => {
+                let target = action_path(drive_letter, target.as_str())?;
+                let link = action_path(drive_letter, link.as_str())?;
+
+                if link.symlink_metadata().is_ok() {
+                    return Err(action_error(std::io::ErrorKind::AlreadyExists.into(), "Unable to create symlink, file exists", link.as_path()));
+                }
+
+                println!("Linking {} to {}", link.display(), target.display());
+                if target.is_dir() {
+                    std::os::windows::fs::symlink_dir(target.as_path(), link.as_path())
+                } else {
+                    std::os::windows::fs::symlink_file(target.as_path(), link.as_path())
+                }.map_err(|e| action_error(e, "Failed to create symlink", link.as_path()))?;
+            },
+            Self::PatchJson { file, set, encoding } => {
+                let path = action_path(drive_letter, file.as_str())?;
+                println!("Patching {}", path.display());
+                patch::patch_file(path.as_path(), encoding, |c| patch::patch_json(c, &set))
+                    .map_err(|e| action_error(e, "Failed to patch", path.as_path()))?;
+            },
+            Self::PatchIni { file, set, encoding } => {
+                let path = action_path(drive_letter, file.as_str())?;
+                println!("Patching {}", path.display());
+                patch::patch_file(path.as_path(), encoding, |c| Ok(patch::patch_ini(c, &set)))
+                    .map_err(|e| action_error(e, "Failed to patch", path.as_path()))?;
+            },
+            Self::PatchXml { file, set, encoding } => {
+                let path = action_path(drive_letter, file.as_str())?;
+                println!("Patching {}", path.display());
+                patch::patch_file(path.as_path(), encoding, |c| patch::patch_xml(c, &set))
+                    .map_err(|e| action_error(e, "Failed to patch", path.as_path()))?;
+            },
+            Self::RegSet { .. } | Self::RegDelete { .. } => {
+                return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Registry actions are performed by the bridge"));
             }
         }
        
@@ -480,12 +1686,266 @@ impl Action {
     }
 }
 
+/// Value of a registry action, in json as `"value_type"` and `"data"`
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "value_type", content = "data")]
+pub enum RegistryValue {
+    String(String),
+    ExpandString(String),
+    MultiString(Vec<String>),
+    Dword(u32),
+    Qword(u64),
+    Binary(Vec<u8>)
+}
+
+impl RegistryValue {
+    /// Replaces the placeholders in String and MultiString data, returns the unknown ones
+    pub fn expand_templates(&mut self, vars: &template::TemplateVars) -> Vec<String> {
+        let mut unknown = Vec::new();
+        match self {
+            Self::String(text) => vars.expand_in(text, &mut unknown),
+            Self::MultiString(list) => {
+                for text in list.iter_mut() {
+                    vars.expand_in(text, &mut unknown);
+                }
+            },
+            Self::ExpandString(_) | Self::Dword(_) | Self::Qword(_) | Self::Binary(_) => ()
+        }
+        unknown
+    }
+
+    /// Returns the REG_* type id
+    pub fn get_type_id(&self) -> u32 {
+        match self {
+            Self::String(_) => 1,
+            Self::ExpandString(_) => 2,
+            Self::Binary(_) => 3,
+            Self::Dword(_) => 4,
+            Self::MultiString(_) => 7,
+            Self::Qword(_) => 11
+        }
+    }
+
+    /// Returns the data as stored in the registry
+    pub fn to_bytes(&self) -> Vec<u8> {
+        fn wide(text: &str) -> impl Iterator<Item = u8> + '_ {
+            text.encode_utf16().chain(std::iter::once(0)).flat_map(|u| u.to_le_bytes())
+        }
+
+        match self {
+            Self::String(text) | Self::ExpandString(text) => wide(text).collect(),
+            Self::MultiString(list) => list.iter().flat_map(|t| wide(t)).chain([0, 0]).collect(),
+            Self::Dword(value) => value.to_le_bytes().to_vec(),
+            Self::Qword(value) => value.to_le_bytes().to_vec(),
+            Self::Binary(data) => data.clone()
+        }
+    }
+}
+
+/// Root of a registry key
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegistryRoot {
+    ClassesRoot,
+    CurrentUser,
+    LocalMachine,
+    Users
+}
+
+impl RegistryRoot {
+    /// Splits a key like `HKCU\Software\Tool` (or with `HKEY_CURRENT_USER`) into root and subkey.
+    ///
+    /// None if the root is unknown or the subkey is empty
+    pub fn split_key(key: &str) -> Option<(Self, &str)> {
+        let (root, subkey) = key.split_once(['\\', '/'])?;
+
+        let root = match root.to_ascii_uppercase().as_str() {
+            "HKCR" | "HKEY_CLASSES_ROOT" => Self::ClassesRoot,
+            "HKCU" | "HKEY_CURRENT_USER" => Self::CurrentUser,
+            "HKLM" | "HKEY_LOCAL_MACHINE" => Self::LocalMachine,
+            "HKU" | "HKEY_USERS" => Self::Users,
+            _ => return None
+        };
+
+        let subkey = subkey.trim_matches(['\\', '/']);
+        if subkey.is_empty() {
+            return None;
+        }
+
+        Some((root, subkey))
+    }
+}
+
+/// A file or folder that is replaced while the game runs, and restored afterwards
+///
+/// Paths are converted like the ones of apps
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Overlay {
+    /// The file or folder that is replaced (it does not have to exist)
+    target: String,
+    /// What is put in its place
+    source: String,
+
+    #[serde(default, skip_serializing_if = "OverlayMode::is_default")]
+    mode: OverlayMode
+}
+
+impl Overlay {
+    /// Overlays target with a copy of source
+    pub fn new(target: String, source: String) -> Self {
+        Self { target, source, mode: OverlayMode::default() }
+    }
+
+    /// Sets how source is put in place of target
+    pub fn with_mode(mut self, mode: OverlayMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn get_target(&self) -> &str {
+        self.target.as_str()
+    }
+
+    pub fn get_source(&self) -> &str {
+        self.source.as_str()
+    }
+
+    pub fn get_mode(&self) -> OverlayMode {
+        self.mode
+    }
+
+    /// Replaces the placeholders in target and source, returns the unknown ones
+    pub fn expand_templates(&mut self, vars: &template::TemplateVars) -> Vec<String> {
+        let mut unknown = Vec::new();
+        vars.expand_in(&mut self.target, &mut unknown);
+        vars.expand_in(&mut self.source, &mut unknown);
+        unknown
+    }
+
+    /// Converts the paths and applies this overlay within the session
+    #[cfg(target_os = "windows")]
+    pub fn apply(&self, drive_letter: char, session: &mut overlay::OverlaySession) -> std::io::Result<()> {
+        let source = action_path(drive_letter, self.source.as_str())?;
+        let target = action_path(drive_letter, self.target.as_str())?;
+
+        println!("Overlaying {} with {}", target.display(), source.display());
+        session.apply(source.as_path(), target.as_path(), self.mode)
+    }
+}
+
+/// How the source of an overlay is put in place
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum OverlayMode {
+    #[default]
+    Copy,
+    /// Useful for large files or folders, but some games do not follow links
+    Symlink
+}
+
+impl OverlayMode {
+    fn is_default(&self) -> bool {
+        self == &Self::Copy
+    }
+}
+
+/// Adds what we were doing to the io error
+fn action_error(e: std::io::Error, msg: &str, path: &std::path::Path) -> std::io::Error {
+    std::io::Error::new(e.kind(), format!("{msg} '{}': {e}", path.display()))
+}
+
+#[cfg(target_os = "windows")]
+fn action_path(drive_letter: char, path: &str) -> std::io::Result<PathBuf> {
+    let converted = convert_path(drive_letter, path)
+        .ok_or(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Unable to convert path '{path}'")))?;
+
+    Ok(PathBuf::from(converted))
+}
+
+/// Converts from and to, expanding wildcards in from. Errors if nothing matches from
+#[cfg(target_os = "windows")]
+fn action_source_target(drive_letter: char, from: &str, to: &str) -> std::io::Result<(Vec<PathBuf>, PathBuf)> {
+    let from = action_path(drive_letter, from)?;
+    let to = action_path(drive_letter, to)?;
+
+    let sources = expand_wildcards(from.as_path())?;
+    if sources.is_empty() {
+        return Err(action_error(std::io::ErrorKind::NotFound.into(), "Nothing to copy/move at", from.as_path()));
+    }
+
+    if sources.len() > 1 || from.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.contains(['*', '?'])) {
+        // Multiple files go into a folder
+        fs::create_dir_all(to.as_path()).map_err(|e| action_error(e, "Failed to create folder", to.as_path()))?;
+    }
+
+    Ok((sources, to))
+}
+
+/// Returns all existing files matching the wildcards in the last part of the path,
+/// or just the path if it has none (and exists)
+#[cfg(target_os = "windows")]
+fn expand_wildcards(path: &std::path::Path) -> std::io::Result<Vec<PathBuf>> {
+    let pattern = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+    if !pattern.contains(['*', '?']) {
+        return Ok(if path.symlink_metadata().is_ok() { vec![path.to_path_buf()] } else { vec![] });
+    }
+
+    let folder = match path.parent() {
+        Some(folder) if folder.is_dir() => folder,
+        _ => return Ok(vec![])
+    };
+
+    let mut matches = Vec::new();
+    for entry in fs::read_dir(folder).map_err(|e| action_error(e, "Failed to read folder", folder))? {
+        let entry = entry?;
+        if entry.file_name().to_str().is_some_and(|n| wildcard_match(pattern, n)) {
+            matches.push(entry.path());
+        }
+    }
+    matches.sort();
+
+    Ok(matches)
+}
+
+/// Where from ends up, errors if it exists and we can't overwrite
+#[cfg(target_os = "windows")]
+fn target_path(from: &std::path::Path, to: &std::path::Path, overwrite: bool) -> std::io::Result<PathBuf> {
+    let target = if to.is_dir() {
+        to.join(from.file_name().unwrap_or_default())
+    } else {
+        to.to_path_buf()
+    };
+
+    if !overwrite && target.exists() {
+        return Err(action_error(std::io::ErrorKind::AlreadyExists.into(), "Target exists and overwrite is not set", target.as_path()));
+    }
+
+    Ok(target)
+}
+
+/// Copies the file, or the folder with all content
+fn copy_all(from: &std::path::Path, to: &std::path::Path) -> std::io::Result<()> {
+    if !from.is_dir() {
+        fs::copy(from, to).map_err(|e| action_error(e, "Failed to copy", from))?;
+        return Ok(());
+    }
+
+    fs::create_dir_all(to).map_err(|e| action_error(e, "Failed to create folder", to))?;
+    for entry in fs::read_dir(from).map_err(|e| action_error(e, "Failed to read folder", from))? {
+        let entry = entry?;
+        copy_all(entry.path().as_path(), to.join(entry.file_name()).as_path())?;
+    }
+
+    Ok(())
+}
+
 /// Converts a linux path to a path to a wine/windows path.  
 ///   
 /// This is done by converting the slashes and adding appropriate drive letter.  
 ///
 /// Default letter is usually Z
-#[cfg(target_os = "windows")]
 pub fn convert_linux_path_to_wine(drive_letter: char, path: String) -> String {
     let p = path.replace('/', "\\");
     let complete = format!("{}:", drive_letter.to_ascii_uppercase()) + &p;
@@ -499,7 +1959,7 @@ pub fn convert_linux_path_to_wine(drive_letter: char, path: String) -> String {
 #[cfg(target_os = "windows")]
 pub fn get_config_folder_path() -> Option<PathBuf> {
     let mut path = dirs::config_dir()?;
-    path.push("Datalink");
+    path.push(CONFIG_FOLDER_NAME);
     Some(path)
 }
 
@@ -508,42 +1968,56 @@ pub fn get_config_folder_path() -> Option<PathBuf> {
 ///
 /// Used by Datalink
 #[cfg(target_os = "windows")]
-pub fn read_config(default_config: Option<GameBridgeConfig>) -> (Option<(GameBridgeConfig, Option<Vec<String>>)>, Result<(), String>) {
+pub fn read_config(default_config: Option<GameBridgeConfig>) -> (Option<(GameBridgeConfig, Option<Vec<String>>)>, Result<(), ConfigErrors>) {
+    read_config_with_policy(default_config, None)
+}
+
+/// Same as `read_config`, but every config file is checked against the policy (if any)
+///
+/// Used by Datalink
+#[cfg(target_os = "windows")]
+pub fn read_config_with_policy(default_config: Option<GameBridgeConfig>, policy: Option<&policy::Policy>) -> (Option<(GameBridgeConfig, Option<Vec<String>>)>, Result<(), ConfigErrors>) {
+    let (res, err, _) = read_config_with_report(default_config, policy);
+    (res, err)
+}
+
+/// Same as `read_config_with_policy`, additionally returning where the entries of the merged
+/// config came from
+///
+/// Used by Datalink
+#[cfg(target_os = "windows")]
+#[allow(clippy::type_complexity)]
+pub fn read_config_with_report(default_config: Option<GameBridgeConfig>, policy: Option<&policy::Policy>) -> (Option<(GameBridgeConfig, Option<Vec<String>>)>, Result<(), ConfigErrors>, report::MergeReport) {
     let path = match get_config_folder_path() {
         Some(p) => p,
-        None => return (None, Ok(()))
+        None => return (None, Ok(()), report::MergeReport::default())
     };
 
-    if !path.exists() {
+    let existed = path.exists();
+    if !existed {
         if let Err(e) = std::fs::create_dir(path.as_path()) {
-            return (None, Err(format!("Failed to created none existant folder: {e}")));
+            return (None, Err(ConfigError::io(path.as_path(), e).into()), report::MergeReport::default());
         }
+    }
 
-        if let Some(conf) = default_config {
-            let mut file = path.clone();
-            file.push("datalink-default.json");
-            let _ = manual_write_config(&file, conf, false);
-        }
-    } else if let Some(conf) = default_config {
+    if let Some(conf) = default_config {
         let mut file = path.clone();
-        file.push("datalink-default.json");
-
-        // One way: checking if the versions missmatch and then replace
-        // if let Ok(old_conf) = manual_read_config(&file) {
-        //     if old_conf.get_notes() != conf.get_notes() {
-        //         let _ = manual_write_config(&file, conf, true);
-        //     }
-        // }
-
-        // Other way: Replace it, always...
-        if file.exists() {
-            // Well, as long as the file exists. If it doesn't, the user might have disabled it, so
-            // we won't write it again
-            let _ = manual_write_config(&file, conf, true);
+        file.push(presets::PRESET_FILE);
+
+        // If the folder exists but the preset doesn't, the user deleted it to disable it, so we
+        // won't write it again.
+        // Otherwise it is installed like any tool config: only replaced by a newer version (keeping
+        // the user section and enabled flag), and not at all if it fails to parse
+        if !existed || file.exists() {
+            let version = conf.get_tool_version().unwrap_or_default();
+            match install::manual_ensure_config(&file, conf, version) {
+                Ok(outcome) => println!("{}: {outcome}", presets::PRESET_FILE),
+                Err(e) => println!("Failed to update {}: {e}", presets::PRESET_FILE)
+            }
         }
     }
 
-    manual_read_configs_from_folder(&path)
+    manual_read_configs_from_folder_with_report(&path, policy)
 }
 
 /// Finds the folder within the prefix
@@ -554,7 +2028,7 @@ pub(crate) fn get_path_from_prefix(game_drive: &proton_finder::GameDrive) -> Opt
         return None;
     }
 
-    path.push("Datalink");
+    path.push(CONFIG_FOLDER_NAME);
     if !path.exists() {
         // Folder does not exist, so we create it
         fs::create_dir(path.as_path()).ok()?;
@@ -566,35 +2040,36 @@ pub(crate) fn get_path_from_prefix(game_drive: &proton_finder::GameDrive) -> Opt
 
 /// Writes to config to a path you specified.
 ///
-/// If a file already exists, and overwrite is false, then no file is written, and returns false.
-/// It can also return false if the write fails.
+/// If a file already exists, and overwrite is false, then no file is written, and
+/// `ConfigError::AlreadyExists` is returned.
+/// The file is written atomically (the bridge never reads a half written config), while holding
+/// the lock on the folder (see `lock::FolderLock`).
+/// It is written as toml if the path ends in `.toml`, json otherwise
 ///
 /// Manual forces you to make sure it is written where the Datalink bridge can read it, effectively: 
-/// C:\Users\[current]\AppData\Roaming\Datalink\*.json
+/// C:\Users\[current]\AppData\Roaming\Datalink\*.json (or *.toml)
 /// Using the proton feature allows you to use proton-finder and skip finding the path
-pub fn manual_write_config(path: &PathBuf, config: GameBridgeConfig, overwrite: bool) -> bool {
-    if let Ok(text) = serde_json::to_string_pretty(&config) {
-        if path.exists() && !overwrite {
-            return false;
-        }
-
-        if fs::write(path, text).is_ok() {
-            return true;
-        }
-    }
-
-
-    false
+pub fn manual_write_config(path: &Path, config: GameBridgeConfig, overwrite: bool) -> Result<(), ConfigError> {
+    let folder = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let lock = lock::FolderLock::acquire(folder)?;
+    lock.write_config(path, &config, overwrite)
 }
 
-/// Reads a config at the path you manually specified.
+/// Reads a config at the path you manually specified (json, json with comments or toml, by the extension).
 ///
 /// Manual forces you to make sure this is the correct location, effectively:
 /// C:\Users\[current]\AppData\Roaming\Datalink\*.json
 /// Using the proton feature allows you to use proton-finder and skip finding the path
-pub fn manual_read_config(path: &PathBuf) -> Result<GameBridgeConfig, String> {
-    let conf = fs::read_to_string(path.as_path()).map_err(|e| format!("Failed to read {}: {}", path.to_str().unwrap_or("<no path>"), e))?;
-    serde_json::from_str(&conf).map_err(|e| format!("Failed to parse {}: {}", path.to_str().unwrap_or("<no path>"), e))
+pub fn manual_read_config(path: &Path) -> Result<GameBridgeConfig, ConfigError> {
+    manual_read_config_with_warnings(path).map(|(config, _)| config)
+}
+
+/// Same as `manual_read_config`, additionally returning the warnings (like unknown fields).
+///
+/// Configs written for older versions are migrated (see `schema`)
+pub fn manual_read_config_with_warnings(path: &Path) -> Result<(GameBridgeConfig, Vec<ConfigError>), ConfigError> {
+    let content = fs::read(path).map_err(|e| ConfigError::io(path, e))?;
+    schema::parse_config(path, content.as_slice())
 }
 
 
@@ -608,16 +2083,33 @@ pub fn manual_read_config(path: &PathBuf) -> Result<GameBridgeConfig, String> {
 /// - Maps with the same name we keep the largest
 /// - Apps only match if both exec and arguments match completly (else both are kept)
 ///
-/// If different root_mount_points are set, then the one found first will be used (and a
-/// message is logged out). The order in which is read is not necessarily alphabetic, and could
-/// even change run to run.
+/// Configs are merged from the highest priority down, then sorted by file name. Disabled configs
+/// are skipped, and removals only apply to configs with a lower priority.
+/// If different root_mount_points are set, then the one merged first will be used (and a
+/// message is logged out).
+/// All `.json`, `.jsonc` and `.toml` files in the folder are read.
 ///
 /// Manual forces you to make sure this is the correct location, effecitvely:
 /// C:\Users\[current]\AppData\Roaming\Datalink
 /// Using the proton feature allows you to use proton-finder and skip finding the path
-pub fn manual_read_configs_from_folder(folder: &PathBuf) -> (Option<(GameBridgeConfig, Option<Vec<String>>)>, Result<(), String>) {
+pub fn manual_read_configs_from_folder(folder: &Path) -> (Option<(GameBridgeConfig, Option<Vec<String>>)>, Result<(), ConfigErrors>) {
+    manual_read_configs_from_folder_with_policy(folder, None)
+}
+
+/// Same as `manual_read_configs_from_folder`, but with a policy every config is checked against
+/// before merging (removing apps, actions and overlays it does not allow)
+#[allow(clippy::type_complexity)]
+pub fn manual_read_configs_from_folder_with_policy(folder: &Path, policy: Option<&policy::Policy>) -> (Option<(GameBridgeConfig, Option<Vec<String>>)>, Result<(), ConfigErrors>) {
+    let (res, err, _) = manual_read_configs_from_folder_with_report(folder, policy);
+    (res, err)
+}
+
+/// Same as `manual_read_configs_from_folder_with_policy`, additionally returning a report of
+/// which files contributed each entry, what was deduplicated and where configs conflicted
+#[allow(clippy::type_complexity)]
+pub fn manual_read_configs_from_folder_with_report(folder: &Path, policy: Option<&policy::Policy>) -> (Option<(GameBridgeConfig, Option<Vec<String>>)>, Result<(), ConfigErrors>, report::MergeReport) {
     // Helper function that merges two entries
-    fn merge(read: GameBridgeConfig, res: &mut Option<(GameBridgeConfig, Option<Vec<String>>)>, err: &mut String) {
+    fn merge(read: GameBridgeConfig, res: &mut Option<(GameBridgeConfig, Option<Vec<String>>)>, err: &mut ConfigErrors) {
         let (old, alt_name_list) = if let Some((old, alt_name_list)) = res {
             (old, alt_name_list)
         } else {
@@ -626,20 +2118,20 @@ pub fn manual_read_configs_from_folder(folder: &PathBuf) -> (Option<(GameBridgeC
         };
 
         // Merging mountpoint
-        if !read.root_mount_point.is_default() && !old.root_mount_point.is_default() &&
-            !old.root_mount_point.0.eq_ignore_ascii_case(&read.root_mount_point.0) {
-
-            // Both are set, and to different values
-            // Sort of a problem, but eh, screw it
-            
-            *err = format!("{err}\nTwo configs had different root mountpoints set: {} and {}, using {}", old.get_root_mount_point(), read.get_root_mount_point(), old.get_root_mount_point());
-
-        } else if !old.root_mount_point.is_default() {
-            // Original is unset, so we return the new one
-            // Possible the new one is also unset, but doesn't matter, this will then just unset it again
-            old.root_mount_point.0 = read.root_mount_point.0;
-        } else {
-            // Original is set, new must be unset or the same, so this is fine
+        match (old.root_mount_point, read.root_mount_point) {
+            (Some(old_drive), Some(read_drive)) if !old_drive.0.eq_ignore_ascii_case(&read_drive.0) => {
+                // Both are set, and to different values
+                // Sort of a problem, but eh, screw it
+                err.push(ConfigError::Conflict { message: format!("Two configs had different root mountpoints set: {} and {}, using {}", old_drive.0, read_drive.0, old_drive.0) });
+            },
+            (None, read_drive) => {
+                // Original is unset, so we return the new one
+                // Possible the new one is also unset, but doesn't matter, this will then just unset it again
+                old.root_mount_point = read_drive;
+            },
+            _ => {
+                // Original is set, new must be unset or the same, so this is fine
+            }
         }
 
         // Merging appid override
@@ -692,44 +2184,104 @@ pub fn manual_read_configs_from_folder(folder: &PathBuf) -> (Option<(GameBridgeC
         old.maps.append(&mut read.maps);
 
         old.post_apps.append(&mut read.post_apps);
+
+        old.process_tracking.merge(read.process_tracking);
+
+        old.overlays.append(&mut read.overlays);
+
+        // Merging start trigger
+        if !read.start_trigger.is_default() && !old.start_trigger.is_default() && read.start_trigger != old.start_trigger {
+            err.push(ConfigError::Conflict { message: format!("Two configs had different start triggers set: {:?} and {:?}, using {:?}", old.start_trigger, read.start_trigger, old.start_trigger) });
+        } else if old.start_trigger.is_default() {
+            old.start_trigger = read.start_trigger;
+        }
     }
 
     let dir = match folder.read_dir() {
         Ok(dir) => dir,
-        Err(e) => return (None, Err(format!("Failed to read folder: {e}")))
+        Err(e) => return (None, Err(ConfigError::io(folder, e).into()), report::MergeReport::default())
     };
 
     let mut res:Option<(GameBridgeConfig, Option<Vec<String>>)> = None;
-    let mut err = String::new();
+    let mut err = ConfigErrors::default();
+    let mut report = report::MergeReport::default();
+    let mut configs = Vec::<(String, GameBridgeConfig)>::new();
 
     for item in dir {
         match item {
             Ok(item) => {
-                if let Some(ext) = item.path().extension() {
-                    if ext == "json" {
-                        match manual_read_config(&item.path()) {
-                            Ok(read) => {
-                                merge(read, &mut res, &mut err);
-                            },
-                            Err(e) => {
-                                err = format!("{err}\n{}", e);
+                if format::ConfigFormat::from_path(item.path().as_path()).is_some() {
+                    let name = item.file_name().to_string_lossy().to_string();
+                    let path = item.path();
+                    let read = fs::read(path.as_path()).map_err(|e| ConfigError::io(path.as_path(), e)).and_then(|content| {
+                        let (mut config, warnings) = schema::parse_config(path.as_path(), content.as_slice())?;
+                        for entry in config.apply_user_edits() {
+                            report.removed.push(format!("{entry} from {name}, removed by its user section"));
+                        }
+                        if let Some(policy) = policy {
+                            policy.apply_trust(name.as_str(), content.as_slice(), &mut config);
+                        }
+                        Ok((config, warnings))
+                    });
+
+                    match read {
+                        Ok((read, warnings)) => {
+                            for warning in warnings {
+                                err.push(warning);
                             }
+                            configs.push((name, read));
+                        },
+                        Err(e) => {
+                            err.push(e);
                         }
                     }
-                    // The extension checks do not need an else case, as we just ignore any file
-                    // that doesn't end in json
                 }
+                // The extension check does not need an else case, as we just ignore any file
+                // that is not a json or toml config
             },
             Err(e) => {
-                err = format!("{err}\nUnable to get directory entry: {}", e);
+                err.push(ConfigError::io(folder, e));
             }
         }
     }
 
+    // Highest priority first, then by name, so the result does not depend on the order of the folder
+    configs.sort_by(|(a_name, a), (b_name, b)| b.priority.cmp(&a.priority).then_with(|| a_name.cmp(b_name)));
+
+    let mut removals = Vec::<(String, i32, ConfigRemovals)>::new();
+    for (name, mut read) in configs {
+        if !read.is_enabled() {
+            report.disabled.push(name);
+            continue;
+        }
+
+        // Only configs with a higher priority can remove entries
+        let own_priority = read.priority;
+        for (remover, _, remove) in removals.iter().filter(|(_, priority, _)| *priority > own_priority) {
+            for entry in remove.apply(&mut read) {
+                report.removed.push(format!("{entry} from {name}, removed by {remover}"));
+            }
+        }
+        if !read.remove.is_empty() {
+            removals.push((name.clone(), read.priority, std::mem::take(&mut read.remove)));
+        }
+
+        report.add_config(name.as_str(), &read);
+        merge(read, &mut res, &mut err);
+    }
+
     // Sanitizing
     if let Some((val, _)) = res.as_mut() {
         val.sanitize();
+
+        // Without executables every process in the game folder matches, including the launcher,
+        // so the game would be considered started right away
+        if val.start_trigger == StartTrigger::Process && val.process_tracking.executables.is_empty() {
+            err.push(ConfigError::Conflict { message: "The Process start trigger requires executables in process_tracking, starting immediately instead".to_string() });
+            val.start_trigger = StartTrigger::Immediately;
+        }
     }
+    report.finish(res.as_ref().map(|(val, _)| val), &err);
 
     let err = if err.is_empty() {
         Ok(())
@@ -737,5 +2289,5 @@ pub fn manual_read_configs_from_folder(folder: &PathBuf) -> (Option<(GameBridgeC
         Err(err)
     };
 
-    (res, err)
+    (res, err, report)
 }
@@ -0,0 +1,82 @@
+//! Advisory lock on a config folder, and atomic writes of configs.
+//!
+//! Files are written into a temporary file next to the config and then renamed over it, so
+//! readers (like the bridge) never see a half written config. The lock is only advisory, it keeps
+//! tools using this crate from overwriting each others changes, but does not stop anyone else.
+
+use std::{fs::{self, File, OpenOptions, TryLockError}, io::Write, path::{Path, PathBuf}, thread, time::{Duration, Instant}};
+
+use crate::{error::ConfigError, format::ConfigFormat, GameBridgeConfig};
+
+/// Name of the lock file within the config folder (no `.json`, so it is not read as a config)
+pub const LOCK_FILE: &str = "datalink.lock";
+
+/// How long to wait for another tool to release the lock
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Exclusive lock on a config folder, released when dropped
+#[derive(Debug)]
+pub struct FolderLock {
+    folder: PathBuf,
+    _file: File
+}
+
+impl FolderLock {
+    /// Waits (up to 10 seconds) until no other tool holds the lock for this folder
+    pub fn acquire(folder: &Path) -> Result<Self, ConfigError> {
+        let path = folder.join(LOCK_FILE);
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(path.as_path())
+            .map_err(|e| ConfigError::io(path.as_path(), e))?;
+
+        let start = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if start.elapsed() < LOCK_TIMEOUT => thread::sleep(Duration::from_millis(50)),
+                Err(TryLockError::WouldBlock) => return Err(ConfigError::Locked { path }),
+                Err(TryLockError::Error(e)) => return Err(ConfigError::io(path.as_path(), e))
+            }
+        }
+
+        Ok(Self { folder: folder.to_path_buf(), _file: file })
+    }
+
+    pub fn get_folder(&self) -> &Path {
+        self.folder.as_path()
+    }
+
+    /// Writes the config atomically, while holding this lock (as toml if the path ends in `.toml`, json otherwise).
+    ///
+    /// Use this (instead of `manual_write_config`) to read, modify and write configs without
+    /// another tool changing them in between
+    pub fn write_config(&self, path: &Path, config: &GameBridgeConfig, overwrite: bool) -> Result<(), ConfigError> {
+        let text = ConfigFormat::from_path(path).unwrap_or_default().serialize(config)
+            .map_err(|message| ConfigError::Serialize { path: path.to_path_buf(), message })?;
+
+        if !overwrite && path.exists() {
+            return Err(ConfigError::AlreadyExists { path: path.to_path_buf() });
+        }
+
+        write_atomic(path, text.as_bytes())
+    }
+}
+
+/// Writes into `[name].tmp` next to the file, then renames it over the file
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> Result<(), ConfigError> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let res = File::create(tmp.as_path())
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .map_err(|e| ConfigError::io(tmp.as_path(), e))
+        .and_then(|_| fs::rename(tmp.as_path(), path).map_err(|e| ConfigError::io(path, e)));
+
+    if res.is_err() {
+        let _ = fs::remove_file(tmp.as_path());
+    }
+    res
+}
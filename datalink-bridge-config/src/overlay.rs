@@ -0,0 +1,236 @@
+//! Applying overlays for the duration of a session, and putting the originals back.
+//!
+//! Every change is written to a journal in the config folder before it is made, so if the bridge
+//! was killed (or wine was) the next start can still restore the originals from the backups.
+
+use std::{fs, io, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{action_error, copy_all, OverlayMode};
+
+/// Not ending in json, so it is not read as a config
+const JOURNAL_FILE: &str = "overlay.journal";
+const BACKUP_FOLDER: &str = "overlay-backup";
+
+#[derive(Debug, Deserialize, Serialize)]
+struct JournalEntry {
+    target: PathBuf,
+    /// Where the original was moved to, None if there was no original
+    backup: Option<PathBuf>
+}
+
+/// The overlays applied in this session
+#[derive(Debug)]
+pub struct OverlaySession {
+    folder: PathBuf,
+    entries: Vec<JournalEntry>
+}
+
+impl OverlaySession {
+    /// Starts a session, with journal and backups in the given folder (the config folder).
+    ///
+    /// If the journal of a previous session is still present (so it crashed) those overlays are
+    /// restored first. Anything that failed to restore stays in the journal and is retried
+    /// when this session is restored.
+    pub fn start(folder: PathBuf) -> (Self, Result<(), String>) {
+        let mut session = Self { folder, entries: Vec::new() };
+
+        let journal = session.folder.join(JOURNAL_FILE);
+        if !journal.exists() {
+            return (session, Ok(()));
+        }
+
+        println!("Found overlays of a previous session that did not shut down, restoring them");
+        match fs::read(journal.as_path()).map_err(|e| e.to_string()).and_then(|data| serde_json::from_slice::<Vec<JournalEntry>>(&data).map_err(|e| e.to_string())) {
+            Ok(entries) => {
+                session.entries = entries;
+                let res = session.restore_all();
+                (session, res)
+            },
+            Err(e) => (session, Err(format!("Unable to read overlay journal '{}': {e}", journal.display())))
+        }
+    }
+
+    /// If no overlays are (still) applied
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Backs up target and puts source in its place.
+    ///
+    /// If placing the source fails the original is put back right away
+    pub fn apply(&mut self, source: &Path, target: &Path, mode: OverlayMode) -> io::Result<()> {
+        if source.symlink_metadata().is_err() {
+            return Err(action_error(io::ErrorKind::NotFound.into(), "No overlay source at", source));
+        }
+        if self.entries.iter().any(|e| e.target == target) {
+            return Err(action_error(io::ErrorKind::AlreadyExists.into(), "Overlay already applied to", target));
+        }
+
+        let backup = if target.symlink_metadata().is_ok() {
+            Some(self.free_backup_path(target))
+        } else {
+            None
+        };
+
+        // Journal first, so a crash during any of the following is restored
+        self.entries.push(JournalEntry { target: target.to_path_buf(), backup: backup.clone() });
+        if let Err(e) = self.write_journal() {
+            self.entries.pop();
+            return Err(e);
+        }
+
+        let res = match backup.as_ref() {
+            Some(backup) => move_path(target, backup.as_path()),
+            None => match target.parent() {
+                Some(parent) => fs::create_dir_all(parent).map_err(|e| action_error(e, "Failed to create folder", parent)),
+                None => Ok(())
+            }
+        }.and_then(|_| match mode {
+            OverlayMode::Copy => copy_all(source, target),
+            OverlayMode::Symlink => symlink(source, target)
+        });
+
+        if let Err(e) = res {
+            let entry = self.entries.last().expect("We just pushed it");
+            if restore_entry(entry).is_ok() {
+                self.entries.pop();
+                let _ = self.write_journal();
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Restores all originals (in reverse order), and removes the journal once everything is restored
+    pub fn restore(mut self) -> Result<(), String> {
+        self.restore_all()
+    }
+
+    fn restore_all(&mut self) -> Result<(), String> {
+        let mut err = String::new();
+        let mut failed = Vec::new();
+
+        while let Some(entry) = self.entries.pop() {
+            match restore_entry(&entry) {
+                Ok(()) => println!("Restored {}", entry.target.display()),
+                Err(e) => {
+                    err = format!("{err}\nFailed to restore overlay: {e}");
+                    failed.push(entry);
+                }
+            }
+        }
+        failed.reverse();
+        self.entries = failed;
+
+        if self.entries.is_empty() {
+            let _ = fs::remove_file(self.folder.join(JOURNAL_FILE));
+            // Only succeeds if empty, which it should be now
+            let _ = fs::remove_dir(self.folder.join(BACKUP_FOLDER));
+        } else if let Err(e) = self.write_journal() {
+            err = format!("{err}\n{e}");
+        }
+
+        if err.is_empty() {
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    fn write_journal(&self) -> io::Result<()> {
+        fs::create_dir_all(self.folder.as_path()).map_err(|e| action_error(e, "Failed to create folder", self.folder.as_path()))?;
+
+        let journal = self.folder.join(JOURNAL_FILE);
+        let data = serde_json::to_vec_pretty(&self.entries).map_err(io::Error::other)?;
+
+        // Renaming over it, so a crash mid write does not leave a broken journal behind
+        let temp = journal.with_extension("journal.tmp");
+        fs::write(temp.as_path(), data).map_err(|e| action_error(e, "Failed to write overlay journal", temp.as_path()))?;
+        fs::rename(temp.as_path(), journal.as_path()).map_err(|e| action_error(e, "Failed to write overlay journal", journal.as_path()))
+    }
+
+    /// A path in the backup folder not used by another backup (or leftover of a failed restore)
+    fn free_backup_path(&self, target: &Path) -> PathBuf {
+        let name = target.file_name().and_then(|n| n.to_str()).unwrap_or("overlay");
+        let folder = self.folder.join(BACKUP_FOLDER);
+
+        let mut index = 0_usize;
+        loop {
+            let path = folder.join(format!("{index}-{name}"));
+            if path.symlink_metadata().is_err() && !self.entries.iter().any(|e| e.backup.as_ref() == Some(&path)) {
+                return path;
+            }
+            index += 1;
+        }
+    }
+}
+
+fn restore_entry(entry: &JournalEntry) -> io::Result<()> {
+    match entry.backup.as_ref() {
+        Some(backup) if backup.symlink_metadata().is_ok() => {
+            remove_path(entry.target.as_path())?;
+            move_path(backup.as_path(), entry.target.as_path())
+        },
+        // Crashed before the original was moved, so it is still in place
+        Some(_) => Ok(()),
+        // There was nothing, so whatever is there is ours
+        None => remove_path(entry.target.as_path())
+    }
+}
+
+/// Renames, and copies if that fails (the config folder might be on a different filesystem)
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| action_error(e, "Failed to create folder", parent))?;
+    }
+
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    // Copying next to it first, so an incomplete copy is never mistaken for the full one
+    let mut partial = to.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    remove_path(partial.as_path())?;
+    copy_all(from, partial.as_path())?;
+    fs::rename(partial.as_path(), to).map_err(|e| action_error(e, "Failed to move", partial.as_path()))?;
+
+    remove_path(from)
+}
+
+/// Removes the file, folder or symlink, if it exists
+fn remove_path(path: &Path) -> io::Result<()> {
+    let meta = match path.symlink_metadata() {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(action_error(e, "Failed to read", path))
+    };
+
+    if meta.is_symlink() {
+        // On windows links to folders have to be removed as folders
+        fs::remove_file(path).or_else(|_| fs::remove_dir(path))
+    } else if meta.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }.map_err(|e| action_error(e, "Failed to remove", path))
+}
+
+#[cfg(target_os = "windows")]
+fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    if source.is_dir() {
+        std::os::windows::fs::symlink_dir(source, target)
+    } else {
+        std::os::windows::fs::symlink_file(source, target)
+    }.map_err(|e| action_error(e, "Failed to create symlink", target))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target).map_err(|e| action_error(e, "Failed to create symlink", target))
+}
@@ -0,0 +1,361 @@
+//! Patching of game config files (JSON, INI and XML), used by the Patch actions.
+//!
+//! Only the set keys are changed, the rest of the file is kept as is (as far as the format
+//! allows, JSON is reformatted). Missing files are created.
+
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Text encoding of a patched file.
+///
+/// Existing files keep their encoding, this is only used when creating them
+/// (some games, like ACC, only read UTF-16 files)
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le
+}
+
+impl TextEncoding {
+    const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+    const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+
+    /// Decodes the file content, detecting the encoding through the byte order mark
+    pub fn decode(bytes: &[u8]) -> Result<(String, TextEncoding), String> {
+        if let Some(rest) = bytes.strip_prefix(&Self::UTF16LE_BOM) {
+            if rest.len() % 2 != 0 {
+                return Err("Invalid UTF-16 file, odd length".to_string());
+            }
+
+            let units: Vec<u16> = rest.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+            let text = String::from_utf16(&units).map_err(|e| format!("Invalid UTF-16 file: {e}"))?;
+            return Ok((text, Self::Utf16Le));
+        }
+
+        let (rest, encoding) = match bytes.strip_prefix(&Self::UTF8_BOM) {
+            Some(rest) => (rest, Self::Utf8Bom),
+            None => (bytes, Self::Utf8)
+        };
+        let text = String::from_utf8(rest.to_vec()).map_err(|e| format!("Invalid UTF-8 file: {e}"))?;
+
+        Ok((text, encoding))
+    }
+
+    /// Encodes the text, including the byte order mark if the encoding has one
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self {
+            Self::Utf8 => text.as_bytes().to_vec(),
+            Self::Utf8Bom => Self::UTF8_BOM.iter().copied().chain(text.bytes()).collect(),
+            Self::Utf16Le => Self::UTF16LE_BOM.iter().copied().chain(text.encode_utf16().flat_map(|u| u.to_le_bytes())).collect()
+        }
+    }
+}
+
+/// Reads the file (or an empty text if it does not exist), patches it and writes it back
+/// (in the same encoding) if anything changed
+pub fn patch_file<F>(path: &Path, encoding: Option<TextEncoding>, patch: F) -> io::Result<()>
+where F: FnOnce(&str) -> Result<String, String> {
+    let (content, encoding) = if path.exists() {
+        TextEncoding::decode(fs::read(path)?.as_slice()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+    } else {
+        (String::new(), encoding.unwrap_or_default())
+    };
+
+    let patched = patch(content.as_str()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if patched == content && path.exists() {
+        return Ok(());
+    }
+
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder)?;
+    }
+    fs::write(path, encoding.encode(patched.as_str()))
+}
+
+/// Sets the values in the json.
+///
+/// Keys are JSON pointers (`/parent/child`, `~1` for a `/` and `~0` for a `~` in a name),
+/// missing objects are created. A key without leading `/` is a top level key.
+pub fn patch_json(content: &str, set: &BTreeMap<String, Value>) -> Result<String, String> {
+    let mut json = if content.trim().is_empty() {
+        Value::Object(serde_json::Map::new())
+    } else {
+        serde_json::from_str::<Value>(content).map_err(|e| format!("Failed to parse json: {e}"))?
+    };
+
+    for (key, value) in set {
+        let tokens: Vec<String> = match key.strip_prefix('/') {
+            Some(pointer) => pointer.split('/').map(|t| t.replace("~1", "/").replace("~0", "~")).collect(),
+            None => vec![key.clone()]
+        };
+
+        let mut current = &mut json;
+        for token in tokens {
+            current = match current {
+                Value::Object(map) => map.entry(token).or_insert(Value::Null),
+                Value::Array(list) => {
+                    let index = if token == "-" {
+                        list.len()
+                    } else {
+                        token.parse::<usize>().map_err(|_| format!("{key}: {token} is not an array index"))?
+                    };
+
+                    if index == list.len() {
+                        list.push(Value::Null);
+                    }
+                    list.get_mut(index).ok_or(format!("{key}: index {index} is out of bounds"))?
+                },
+                Value::Null => {
+                    *current = Value::Object(serde_json::Map::new());
+                    current.as_object_mut().expect("Just set").entry(token).or_insert(Value::Null)
+                },
+                _ => return Err(format!("{key}: {token} can not be set, parent is not an object"))
+            };
+        }
+
+        *current = value.clone();
+    }
+
+    // Keeping the indentation and line endings of the file
+    let indent = content.lines()
+        .find_map(|l| {
+            let trimmed = l.trim_start();
+            (!trimmed.is_empty() && trimmed.len() != l.len()).then(|| &l[..l.len() - trimmed.len()])
+        })
+        .unwrap_or("  ");
+
+    let mut output = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+    json.serialize(&mut serializer).map_err(|e| format!("Failed to write json: {e}"))?;
+    let mut output = String::from_utf8(output).map_err(|e| format!("Failed to write json: {e}"))?;
+
+    if content.ends_with('\n') {
+        output.push('\n');
+    }
+    Ok(restore_line_endings(content, output))
+}
+
+/// Sets the values in the ini, grouped by section (empty for keys before the first section).
+///
+/// Keys are matched case insensitive, missing keys are added at the end of their section, missing
+/// sections at the end of the file. Comments and everything else is kept.
+pub fn patch_ini(content: &str, set: &BTreeMap<String, BTreeMap<String, String>>) -> String {
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+    let mut done = BTreeMap::<(String, String), bool>::new();
+    for (section, keys) in set {
+        for key in keys.keys() {
+            done.insert((section.to_lowercase(), key.to_lowercase()), false);
+        }
+    }
+
+    // Replacing existing keys, and remembering where each section ends
+    let mut section = String::new();
+    let mut section_ends = BTreeMap::<String, usize>::new();
+    section_ends.insert(String::new(), 0);
+
+    for (index, line) in lines.iter_mut().enumerate() {
+        let trimmed = line.trim();
+
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            section = name.trim().to_lowercase();
+            section_ends.insert(section.clone(), index + 1);
+            continue;
+        }
+
+        if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some((key, _)) = line.split_once('=') {
+            section_ends.insert(section.clone(), index + 1);
+
+            let id = (section.clone(), key.trim().to_lowercase());
+            if let Some(false) = done.get(&id) {
+                let value = set.iter()
+                    .find(|(s, _)| s.to_lowercase() == id.0)
+                    .and_then(|(_, keys)| keys.iter().find(|(k, _)| k.to_lowercase() == id.1))
+                    .map(|(_, v)| v)
+                    .expect("Key is in done, so it is in set");
+
+                // Keeping the key as written, including the spacing around the =
+                let spacing = line[key.len() + 1..].len() - line[key.len() + 1..].trim_start().len();
+                *line = format!("{key}={}{value}", &line[key.len() + 1..key.len() + 1 + spacing]);
+                done.insert(id, true);
+            }
+        }
+    }
+
+    // Adding missing keys, from the back so the indices stay valid
+    let mut inserts = Vec::<(usize, Vec<String>)>::new();
+    let mut new_sections = Vec::<String>::new();
+
+    for (section, keys) in set {
+        let missing: Vec<String> = keys.iter()
+            .filter(|(k, _)| done.get(&(section.to_lowercase(), k.to_lowercase())) == Some(&false))
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+
+        match section_ends.get(&section.to_lowercase()) {
+            Some(end) => inserts.push((*end, missing)),
+            None => {
+                if !new_sections.is_empty() || lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                    new_sections.push(String::new());
+                }
+                new_sections.push(format!("[{section}]"));
+                new_sections.extend(missing);
+            }
+        }
+    }
+
+    inserts.sort_by_key(|(index, _)| std::cmp::Reverse(*index));
+    for (index, keys) in inserts {
+        lines.splice(index..index, keys);
+    }
+    lines.extend(new_sections);
+
+    let mut output = lines.join("\n");
+    if content.ends_with('\n') || content.is_empty() {
+        output.push('\n');
+    }
+    restore_line_endings(content, output)
+}
+
+/// Sets the values in the xml.
+///
+/// Keys are the path of elements from the root (`root/child/value`) setting the text, or with an
+/// attribute (`root/child@name`) setting the attribute. Missing elements are created.
+/// The file is edited in place, so everything else is kept as is.
+pub fn patch_xml(content: &str, set: &BTreeMap<String, String>) -> Result<String, String> {
+    let mut text = content.to_string();
+
+    for (key, value) in set {
+        let (path, attribute) = match key.split_once('@') {
+            Some((path, attribute)) => (path, Some(attribute)),
+            None => (key.as_str(), None)
+        };
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        if segments.is_empty() {
+            return Err(format!("{key}: no element set"));
+        }
+        let value = escape_xml(value);
+
+        if text.trim().is_empty() {
+            text = format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n{}\n", build_xml(&segments, attribute, value.as_str()));
+            continue;
+        }
+
+        let options = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+        let doc = roxmltree::Document::parse_with_options(text.as_str(), options).map_err(|e| format!("Failed to parse xml: {e}"))?;
+        let root = doc.root_element();
+        if root.tag_name().name() != segments[0] {
+            return Err(format!("{key}: root element is {}, not {}", root.tag_name().name(), segments[0]));
+        }
+
+        // Finding the deepest existing element
+        let mut node = root;
+        let mut depth = 1;
+        while let Some(child) = segments.get(depth).and_then(|s| node.children().find(|c| c.is_element() && c.tag_name().name() == *s)) {
+            node = child;
+            depth += 1;
+        }
+
+        let start_tag_end = find_start_tag_end(text.as_str(), node.range().start);
+        let self_closing = text[..start_tag_end].ends_with("/>");
+
+        let (range, replacement) = if depth < segments.len() {
+            // Missing elements are created inside the deepest existing one
+            let inner = build_xml(&segments[depth..], attribute, value.as_str());
+            insert_into(text.as_str(), node, start_tag_end, self_closing, inner)
+        } else if let Some(attribute) = attribute {
+            match node.attributes().find(|a| a.name() == attribute) {
+                Some(attr) => (attr.range_value(), value),
+                None => {
+                    // Right after the tag name
+                    let name_end = node.range().start + 1 + text[node.range().start + 1..]
+                        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+                        .unwrap_or(0);
+                    (name_end..name_end, format!(" {attribute}=\"{value}\""))
+                }
+            }
+        } else if node.children().any(|c| c.is_element()) {
+            return Err(format!("{key}: element has child elements, can not set its text"));
+        } else if let Some(child) = node.children().find(|c| c.is_text()) {
+            (child.range(), value)
+        } else {
+            insert_into(text.as_str(), node, start_tag_end, self_closing, value)
+        };
+
+        text.replace_range(range, replacement.as_str());
+    }
+
+    Ok(text)
+}
+
+/// Returns the position after the `>` of the start tag beginning at start
+fn find_start_tag_end(text: &str, start: usize) -> usize {
+    let mut quote = None;
+
+    for (index, c) in text[start..].char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return start + index + 1,
+            _ => ()
+        }
+    }
+
+    text.len()
+}
+
+/// Returns the edit inserting inner as the last content of the node
+fn insert_into(text: &str, node: roxmltree::Node, start_tag_end: usize, self_closing: bool, inner: String) -> (std::ops::Range<usize>, String) {
+    if self_closing {
+        let close = start_tag_end - 2;
+        (close..start_tag_end, format!(">{inner}</{}>", &text[node.range().start + 1..node.range().start + 1 + node_name_len(text, node)]))
+    } else {
+        let end_tag = text[..node.range().end].rfind("</").unwrap_or(node.range().end);
+        (end_tag..end_tag, inner)
+    }
+}
+
+fn node_name_len(text: &str, node: roxmltree::Node) -> usize {
+    text[node.range().start + 1..]
+        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .unwrap_or(0)
+}
+
+/// Builds the nested elements, setting value as text or attribute of the last one
+fn build_xml(segments: &[&str], attribute: Option<&str>, value: &str) -> String {
+    let (last, parents) = segments.split_last().expect("At least one segment");
+
+    let mut output = match attribute {
+        Some(attribute) => format!("<{last} {attribute}=\"{value}\"/>"),
+        None => format!("<{last}>{value}</{last}>")
+    };
+    for parent in parents.iter().rev() {
+        output = format!("<{parent}>{output}</{parent}>");
+    }
+
+    output
+}
+
+fn escape_xml(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn restore_line_endings(original: &str, output: String) -> String {
+    if original.contains("\r\n") {
+        output.replace('\n', "\r\n")
+    } else {
+        output
+    }
+}
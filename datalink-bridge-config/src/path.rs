@@ -0,0 +1,211 @@
+//! Paths as written in configs, and converting them between the windows and the linux side.
+//!
+//! On windows (in the bridge) conversion only needs the drive letter of the linux root, on linux
+//! the drive letters are resolved through the `dosdevices` symlinks of the prefix.
+
+use std::{fmt::Display, fs, path::{Path, PathBuf}, str::FromStr};
+
+/// A path in a config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigPath {
+    /// Absolute linux path, like `/home/user/tool`
+    Linux(String),
+    /// Absolute windows path, like `C:\Games`. The path is without the drive, separated with `\`
+    Windows { drive: char, path: String },
+    /// Relative to the Datalink config folder (`AppData\Roaming\Datalink`)
+    ConfigRelative(String),
+    /// Relative to the folder in a windows env variable, like `%APPDATA%\Tool`
+    EnvRelative { var: String, path: String }
+}
+
+impl FromStr for ConfigPath {
+    type Err = String;
+
+    /// Uses the same rules as the bridge: drive letter for windows, `/` for linux, `%VAR%` for env
+    /// variables, everything else is relative to the config folder
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let first = chars.next().ok_or("Path is empty".to_string())?;
+        let second = chars.next();
+
+        if first.is_ascii_alphabetic() && second == Some(':') {
+            Ok(Self::Windows { drive: first.to_ascii_uppercase(), path: normalize(&s[2..]) })
+        } else if first == '/' {
+            Ok(Self::Linux(s.to_string()))
+        } else if first == '\\' {
+            Err(format!("Path '{s}' has no drive letter (network paths are not supported)"))
+        } else if first == '{' {
+            Err(format!("Path '{s}' has an unresolved placeholder"))
+        } else if let Some((var, path)) = s.strip_prefix('%').and_then(|rest| rest.split_once('%')) {
+            if var.is_empty() {
+                return Err(format!("Path '{s}' has an empty env variable"));
+            }
+            Ok(Self::EnvRelative { var: var.to_string(), path: normalize(path) })
+        } else {
+            Ok(Self::ConfigRelative(normalize(s.strip_prefix(".\\").or(s.strip_prefix("./")).unwrap_or(s))))
+        }
+    }
+}
+
+impl Display for ConfigPath {
+    /// The path how it would be written in the config
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Linux(path) => write!(f, "{path}"),
+            Self::Windows { drive, path } => write!(f, "{drive}:\\{path}"),
+            Self::ConfigRelative(path) => write!(f, "{path}"),
+            Self::EnvRelative { var, path } if path.is_empty() => write!(f, "%{var}%"),
+            Self::EnvRelative { var, path } => write!(f, "%{var}%\\{path}")
+        }
+    }
+}
+
+impl ConfigPath {
+    /// Converts into a windows path.
+    ///
+    /// `drive_letter` is where the linux root is mounted, `config_dir` the (windows) Datalink config folder.
+    /// Env variables are read from this process, so this is meant for the windows side
+    pub fn to_windows(&self, drive_letter: char, config_dir: &Path) -> Option<String> {
+        let path = match self {
+            Self::Linux(path) => crate::convert_linux_path_to_wine(drive_letter, path.clone()),
+            Self::Windows { .. } => self.to_string(),
+            Self::ConfigRelative(path) => config_dir.join(path).to_str()?.to_string(),
+            Self::EnvRelative { var, path } => {
+                let base = std::env::var(var).ok()?;
+                if path.is_empty() {
+                    base
+                } else {
+                    format!("{}\\{path}", base.trim_end_matches('\\'))
+                }
+            }
+        };
+
+        Some(path)
+    }
+
+    /// Converts into the path on the linux side, for the prefix (the folder containing `drive_c`
+    /// and `dosdevices`, for proton `compatdata/[appid]/pfx`).
+    ///
+    /// Windows paths are matched case insensitive against existing files
+    pub fn to_linux(&self, prefix: &Path) -> Result<PathBuf, String> {
+        match self {
+            Self::Linux(path) => Ok(PathBuf::from(path)),
+            Self::Windows { drive, path } => Ok(join_resolved(drive_root(prefix, *drive)?, path)),
+            Self::ConfigRelative(path) => Ok(join_resolved(env_folder(prefix, "APPDATA")?, format!("Datalink\\{path}").as_str())),
+            Self::EnvRelative { var, path } => Ok(join_resolved(env_folder(prefix, var)?, path))
+        }
+    }
+
+    /// Converts a linux path into the windows path for the prefix, using the drive that
+    /// contains it most specifically (so `C:` over `Z:` for files in `drive_c`)
+    pub fn from_linux(prefix: &Path, path: &Path) -> Result<Self, String> {
+        let (drive, root) = drive_letters(prefix)?.into_iter()
+            .filter(|(_, root)| path.starts_with(root))
+            .max_by_key(|(_, root)| root.components().count())
+            .ok_or(format!("No drive in the prefix contains {}", path.display()))?;
+
+        let rest = path.strip_prefix(root.as_path()).map_err(|e| e.to_string())?;
+        let rest = rest.to_str().ok_or(format!("Path {} is not valid unicode", path.display()))?;
+
+        Ok(Self::Windows { drive: drive.to_ascii_uppercase(), path: normalize(rest) })
+    }
+}
+
+/// All drive letters of the prefix and the folder they point to
+pub fn drive_letters(prefix: &Path) -> Result<Vec<(char, PathBuf)>, String> {
+    let dosdevices = prefix.join("dosdevices");
+    let dir = fs::read_dir(dosdevices.as_path()).map_err(|e| format!("Failed to read {}: {e}", dosdevices.display()))?;
+
+    let mut drives = Vec::new();
+    for entry in dir.filter_map(|e| e.ok()) {
+        let name = entry.file_name();
+        let mut chars = name.to_str().unwrap_or_default().chars();
+
+        // Skipping the devices like com1, and the raw devices like c::
+        let drive = match (chars.next(), chars.next(), chars.next()) {
+            (Some(drive), Some(':'), None) if drive.is_ascii_alphabetic() => drive.to_ascii_uppercase(),
+            _ => continue
+        };
+
+        if let Ok(target) = fs::read_link(entry.path()) {
+            let target = dosdevices.join(target);
+            drives.push((drive, target.canonicalize().unwrap_or(target)));
+        }
+    }
+    drives.sort();
+
+    Ok(drives)
+}
+
+fn drive_root(prefix: &Path, drive: char) -> Result<PathBuf, String> {
+    drive_letters(prefix)?.into_iter()
+        .find(|(d, _)| d.eq_ignore_ascii_case(&drive))
+        .map(|(_, root)| root)
+        .ok_or(format!("No drive {drive}: in prefix {}", prefix.display()))
+}
+
+/// The linux folder for the env variables wine sets, for the user of the prefix
+fn env_folder(prefix: &Path, var: &str) -> Result<PathBuf, String> {
+    let drive_c = drive_root(prefix, 'C')?;
+    let user = || -> Result<PathBuf, String> {
+        let users = join_resolved(drive_c.clone(), "users");
+        let mut names = fs::read_dir(users.as_path()).map_err(|e| format!("Failed to read {}: {e}", users.display()))?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_str().map(|n| n.to_string()))
+            .filter(|n| !n.eq_ignore_ascii_case("Public"))
+            .collect::<Vec<_>>();
+        names.sort();
+
+        // Proton always uses steamuser, plain wine the name of the linux user
+        let name = names.iter().find(|n| n.as_str() == "steamuser").or(names.first()).ok_or(format!("No user in {}", users.display()))?;
+        Ok(users.join(name))
+    };
+
+    let folder = match var.to_lowercase().as_str() {
+        "userprofile" => user()?,
+        "appdata" => join_resolved(user()?, "AppData\\Roaming"),
+        "localappdata" => join_resolved(user()?, "AppData\\Local"),
+        "temp" | "tmp" => join_resolved(user()?, "AppData\\Local\\Temp"),
+        "public" => join_resolved(drive_c, "users\\Public"),
+        "programdata" | "allusersprofile" => join_resolved(drive_c, "ProgramData"),
+        "programfiles" => join_resolved(drive_c, "Program Files"),
+        "programfiles(x86)" => join_resolved(drive_c, "Program Files (x86)"),
+        "windir" | "systemroot" => join_resolved(drive_c, "windows"),
+        "systemdrive" => drive_c,
+        _ => return Err(format!("Env variable %{var}% can not be resolved on the linux side"))
+    };
+
+    Ok(folder)
+}
+
+/// Appends the windows path, using existing files/folders with matching names in a different case
+fn join_resolved(base: PathBuf, rest: &str) -> PathBuf {
+    let mut path = base;
+
+    for part in rest.split(['\\', '/']).filter(|p| !p.is_empty() && *p != ".") {
+        if part == ".." {
+            path.pop();
+            continue;
+        }
+
+        let exact = path.join(part);
+        if exact.symlink_metadata().is_ok() {
+            path = exact;
+            continue;
+        }
+
+        let found = fs::read_dir(path.as_path()).ok().and_then(|dir| {
+            dir.filter_map(|e| e.ok())
+                .find(|e| e.file_name().to_str().is_some_and(|n| n.eq_ignore_ascii_case(part)))
+                .map(|e| e.path())
+        });
+        path = found.unwrap_or(exact);
+    }
+
+    path
+}
+
+/// `\` separated, without leading separators
+fn normalize(path: &str) -> String {
+    path.replace('/', "\\").trim_start_matches('\\').to_string()
+}
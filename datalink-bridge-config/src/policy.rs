@@ -0,0 +1,295 @@
+//! Policy restricting what the configs in the config folder may do.
+//!
+//! Any tool can drop a config into the folder, so the user can put a policy at
+//! `~/.config/Datalink/policy.json` (on the linux side). Datalink passes its path to the bridge,
+//! which checks every config file against it before merging them.
+
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{error::ConfigError, format::ConfigFormat, is_false, template::TemplateVars, wildcard_match, AppContainer, GameBridgeConfig, Overlay};
+
+/// Name of the env variable Datalink sets to the (linux) path of the policy file, if there is one
+pub const POLICY_ENV_VAR: &str = "DATALINK_POLICY";
+
+/// Which configs may contain apps, actions and overlays, and which folders those may touch
+///
+/// A config that is not allowed can still set memory maps, process tracking etc.
+/// The default policy allows no config at all
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct Policy {
+    /// File names of allowed configs, `*` and `?` wildcards supported
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    allowed_configs: Vec<String>,
+
+    /// Sha256 (hex) of allowed config files, so they are only allowed as long as they are unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    allowed_hashes: Vec<String>,
+
+    /// Folders apps, file actions and overlays may touch (converted like app paths), all if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allowed_dirs: Option<Vec<String>>,
+
+    /// No apps, actions and overlays are run, instead the bridge logs what each config would do
+    #[serde(default, skip_serializing_if = "is_false")]
+    dry_run: bool
+}
+
+impl Policy {
+    /// Allows configs with this file name (wildcards supported)
+    pub fn with_allowed_config(mut self, name: String) -> Self {
+        self.allowed_configs.push(name);
+        self
+    }
+
+    /// Allows configs with this sha256
+    pub fn with_allowed_hash(mut self, hash: String) -> Self {
+        self.allowed_hashes.push(hash);
+        self
+    }
+
+    /// Restricts apps, file actions and overlays to this folder (and others added this way)
+    pub fn with_allowed_dir(mut self, dir: String) -> Self {
+        self.allowed_dirs.get_or_insert_with(Vec::new).push(dir);
+        self
+    }
+
+    /// Sets if the bridge should only report what the configs would do
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// If a config file with this name and content may contain apps, actions and overlays
+    pub fn allows_config(&self, file_name: &str, content: &[u8]) -> bool {
+        if self.allowed_configs.iter().any(|pattern| wildcard_match(pattern, file_name)) {
+            return true;
+        }
+
+        if self.allowed_hashes.is_empty() {
+            return false;
+        }
+        let hash = hash_hex(content);
+        self.allowed_hashes.iter().any(|h| h.trim().eq_ignore_ascii_case(hash.as_str()))
+    }
+
+    /// If apps, actions and overlays may touch this path.
+    ///
+    /// The check only looks at the path itself (resolving `..`), it does not follow symlinks.
+    /// Relative paths are only allowed without `allowed_dirs`
+    pub fn allows_path(&self, drive_letter: char, path: &str) -> bool {
+        let dirs = match self.allowed_dirs.as_ref() {
+            Some(dirs) => dirs,
+            None => return true
+        };
+
+        let path = match normalize_path(drive_letter, path) {
+            Some(path) => path,
+            None => return false
+        };
+
+        dirs.iter()
+            .filter_map(|dir| normalize_path(drive_letter, dir.as_str()))
+            .any(|dir| path == dir || path.starts_with(format!("{}\\", dir.trim_end_matches('\\')).as_str()))
+    }
+
+    /// Reads the config at path, removing all apps, actions and overlays if the config is not
+    /// allowed (which is logged)
+    ///
+    /// Paths can contain placeholders, so they are checked with `filter_paths` once those are resolved
+    pub fn read_config(&self, path: &Path) -> Result<GameBridgeConfig, ConfigError> {
+        let (name, content, mut config) = read_file(path)?;
+        self.apply_trust(name.as_str(), content.as_slice(), &mut config);
+        Ok(config)
+    }
+
+    /// Removes all apps, actions and overlays from the (already read) config if it is not allowed
+    pub(crate) fn apply_trust(&self, name: &str, content: &[u8], config: &mut GameBridgeConfig) {
+        if self.allows_config(name, content) {
+            return;
+        }
+
+        for item in config.apps.drain(..).chain(config.post_apps.drain(..)) {
+            println!("Policy skipped {} (from {name}): config is not allowed to run apps and actions", item.to_string());
+        }
+        for overlay in config.overlays.drain(..) {
+            println!("Policy skipped overlay of {} (from {name}): config is not allowed to overlay files", overlay.get_target());
+        }
+    }
+
+    /// Removes the apps, actions and overlays touching paths outside the allowed folders (which is logged).
+    ///
+    /// For apps that is the exec, cwd, wait_for_file and stop command, for overlays target and source
+    pub fn filter_paths(&self, config: &mut GameBridgeConfig) {
+        let drive_letter = config.get_root_mount_point();
+
+        for list in [&mut config.apps, &mut config.post_apps] {
+            list.retain(|item| match self.check_item(true, drive_letter, item) {
+                Ok(()) => true,
+                Err(e) => {
+                    println!("Policy skipped {}: {e}", item.to_string());
+                    false
+                }
+            });
+        }
+        config.overlays.retain(|overlay| match self.check_overlay(true, drive_letter, overlay) {
+            Ok(()) => true,
+            Err(e) => {
+                println!("Policy skipped overlay of {}: {e}", overlay.get_target());
+                false
+            }
+        });
+    }
+
+    /// Lists what every config in the folder would do (with the placeholders resolved),
+    /// and what the policy would skip
+    pub fn report(&self, folder: &Path, vars: &TemplateVars) -> String {
+        let mut files = match fs::read_dir(folder) {
+            Ok(dir) => dir.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| ConfigFormat::from_path(p).is_some()).collect::<Vec<_>>(),
+            Err(e) => return format!("Failed to read folder {}: {e}", folder.display())
+        };
+        files.sort();
+
+        let mut report = String::new();
+        for path in files {
+            let (name, content, config) = match read_file(&path) {
+                Ok(read) => read,
+                Err(e) => {
+                    report = format!("{report}{e}\n");
+                    continue;
+                }
+            };
+            let trusted = self.allows_config(name.as_str(), content.as_slice());
+            let drive_letter = config.get_root_mount_point();
+
+            report = format!("{report}{name} (sha256 {}){}\n", hash_hex(content.as_slice()), if trusted { "" } else { ", not allowed" });
+
+            let apps = config.apps.into_iter().map(|item| ("App", item)).chain(config.post_apps.into_iter().map(|item| ("Post app", item)));
+            for (kind, mut item) in apps {
+                let unknown = item.expand_templates(vars);
+                let res = self.check_item(trusted, drive_letter, &item);
+                report = format!("{report}  {kind} {}: {}\n", item.to_string(), verdict(res));
+                for message in unknown {
+                    report = format!("{report}    Warning: {message}\n");
+                }
+            }
+            for mut overlay in config.overlays {
+                let unknown = overlay.expand_templates(vars);
+                let res = self.check_overlay(trusted, drive_letter, &overlay);
+                report = format!("{report}  Overlay {} with {}: {}\n", overlay.get_target(), overlay.get_source(), verdict(res));
+                for message in unknown {
+                    report = format!("{report}    Warning: {message}\n");
+                }
+            }
+        }
+
+        report
+    }
+
+    fn check_item(&self, trusted: bool, drive_letter: char, item: &AppContainer) -> Result<(), String> {
+        if !trusted {
+            return Err("config is not allowed to run apps and actions".to_string());
+        }
+
+        let paths = match item {
+            AppContainer::App(app) => app.get_paths(),
+            AppContainer::Action(action) => action.get_paths()
+        };
+        if let Some(path) = paths.into_iter().find(|p| !self.allows_path(drive_letter, p)) {
+            return Err(format!("{path} is outside the allowed folders"));
+        }
+
+        Ok(())
+    }
+
+    fn check_overlay(&self, trusted: bool, drive_letter: char, overlay: &Overlay) -> Result<(), String> {
+        if !trusted {
+            return Err("config is not allowed to overlay files".to_string());
+        }
+
+        if let Some(path) = [overlay.get_target(), overlay.get_source()].into_iter().find(|p| !self.allows_path(drive_letter, p)) {
+            return Err(format!("{path} is outside the allowed folders"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the policy file Datalink passed on (via `POLICY_ENV_VAR`), None if there is none.
+///
+/// If it can't be read the default (allowing nothing) is returned, so a broken policy does not
+/// open everything up
+#[cfg(target_os = "windows")]
+pub fn read_policy(drive_letter: char) -> Option<Policy> {
+    let path = std::env::var(POLICY_ENV_VAR).ok()?;
+    let path = crate::convert_path(drive_letter, path.as_str())?;
+
+    let res = fs::read_to_string(path.as_str())
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(text.as_str()).map_err(|e| e.to_string()));
+
+    match res {
+        Ok(policy) => Some(policy),
+        Err(e) => {
+            println!("Failed to read policy {path}: {e}\nNo apps, actions and overlays will be run");
+            Some(Policy::default())
+        }
+    }
+}
+
+fn read_file(path: &Path) -> Result<(String, Vec<u8>, GameBridgeConfig), ConfigError> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+    let content = fs::read(path).map_err(|e| ConfigError::io(path, e))?;
+    let (mut config, _) = crate::schema::parse_config(path, content.as_slice())?;
+    config.apply_user_edits();
+
+    Ok((name, content, config))
+}
+
+fn verdict(res: Result<(), String>) -> String {
+    match res {
+        Ok(()) => "allowed".to_string(),
+        Err(e) => format!("skipped, {e}")
+    }
+}
+
+/// The sha256 of a config file (as `allowed_hashes` expects it)
+pub fn hash_hex(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Lower case windows path with `\` and `.`/`..` resolved, linux paths get the drive letter.
+/// None for relative paths
+fn normalize_path(drive_letter: char, path: &str) -> Option<String> {
+    let path = path.replace('/', "\\");
+    let path = if path.starts_with('\\') {
+        format!("{}:{path}", drive_letter)
+    } else {
+        path
+    };
+
+    let mut chars = path.chars();
+    let drive = match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => drive,
+        _ => return None
+    };
+
+    let mut parts = Vec::new();
+    for part in chars.as_str().split('\\') {
+        match part {
+            "" | "." => (),
+            ".." => {
+                parts.pop();
+            },
+            part => parts.push(part)
+        }
+    }
+
+    Some(format!("{drive}:\\{}", parts.join("\\")).to_lowercase())
+}
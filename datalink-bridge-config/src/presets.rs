@@ -0,0 +1,103 @@
+//! Presets for games Datalink knows the memory maps of.
+//!
+//! The bridge writes the preset for the launched game as `PRESET_FILE` into the config folder,
+//! `datalink-config init` does the same from the linux side.
+
+use crate::{install::ToolVersion, GameBridgeConfig, MemMapConfig};
+
+/// Name of the config file the preset is written to
+pub const PRESET_FILE: &str = "datalink-default.json";
+
+/// Version the presets are stamped with. The bridge and `datalink-config` both install them, so
+/// this is the version of this crate instead of theirs, which could be released apart
+pub const PRESET_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Steam AppIds of all games with a preset
+pub const PRESET_GAME_IDS: [&str; 9] = ["805550", "3058630", "244210", "378860", "1066890", "365960", "227300", "270880", "211500"];
+
+/// The memory maps and the name of the game, None if there is no preset for it
+fn get_preset_maps(game_id: &str) -> Option<(Vec<MemMapConfig>, &'static str)> {
+    let preset = match game_id {
+        "805550" => (vec![
+            MemMapConfig { name: "acpmf_static".to_string(), size: 2048, event: None },
+            MemMapConfig { name: "acpmf_physics".to_string(), size: 2048, event: None },
+            MemMapConfig { name: "acpmf_graphics".to_string(), size: 2048, event: None },
+        ], "Assetto Corsa Competizione"),
+        "3058630" => (vec![
+            MemMapConfig { name: "acpmf_static".to_string(), size: 2048, event: None },
+            MemMapConfig { name: "acpmf_physics".to_string(), size: 2048, event: None },
+            MemMapConfig { name: "acpmf_graphics".to_string(), size: 2048, event: None },
+        ], "Assetto Corsa Evo"),
+        "244210" => (vec![
+            MemMapConfig { name: "acpmf_crewchief".to_string(), size: 15660, event: None },
+            MemMapConfig { name: "acpmf_static".to_string(), size: 2048, event: None },
+            MemMapConfig { name: "acpmf_physics".to_string(), size: 2048, event: None },
+            MemMapConfig { name: "acpmf_graphics".to_string(), size: 2048, event: None },
+        ], "Assetto Corsa"),
+        "378860" => (vec![
+            MemMapConfig { name: "$pcars2$".to_string(), size: 102288, event: None },
+        ], "Project Cars 2"),
+        "1066890" => (vec![
+            MemMapConfig { name: "$pcars2$".to_string(), size: 102288, event: None },
+        ], "Automobilista 2"),
+        "365960" => (vec![
+            // $rFactor2SMMP_Telemetry$ 241680
+            // $rFactor2SMMP_Scoring$ 75304
+            // $rFactor2SMMP_Rules$ 45264
+            // $rFactor2SMMP_MultiRules$ 39788
+            // $rFactor2SMMP_ForceFeedback$ 16
+            // $rFactor2SMMP_Graphics$ 272
+            // $rFactor2SMMP_PitInfo$ 340
+            // $rFactor2SMMP_Weather$ 632
+            // $rFactor2SMMP_Extended$ 10152
+            MemMapConfig { name: "$rFactor2SMMP_Telemetry$".to_string(), size: 241680, event: None },
+            MemMapConfig { name: "$rFactor2SMMP_Scoring$".to_string(), size: 75304, event: None },
+            MemMapConfig { name: "$rFactor2SMMP_Rules$".to_string(), size: 45264, event: None },
+            MemMapConfig { name: "$rFactor2SMMP_MultiRules$".to_string(), size: 39788, event: None },
+            MemMapConfig { name: "$rFactor2SMMP_ForceFeedback$".to_string(), size: 16, event: None },
+            MemMapConfig { name: "$rFactor2SMMP_Graphics$".to_string(), size: 272, event: None },
+            MemMapConfig { name: "$rFactor2SMMP_PitInfo$".to_string(), size: 340, event: None },
+            MemMapConfig { name: "$rFactor2SMMP_Weather$".to_string(), size: 632, event: None },
+            MemMapConfig { name: "$rFactor2SMMP_Extended$".to_string(), size: 10152, event: None },
+        ], "rFactor 2"),
+        // SCSTelemetry, yes the struct is 21619, but the memory map needs 32 * 1024 anyway
+        "227300" => (vec![
+            MemMapConfig { name: "SCSTelemetry".to_string(), size: 32 * 1024, event: None }
+        ], "Euro Truck Simulator 2"),
+        "270880" => (vec![
+            MemMapConfig { name: "SCSTelemetry".to_string(), size: 32 * 1024, event: None }
+        ], "American Truck Simulator"),
+        "211500" => (vec![
+            // $R3E 39320
+            MemMapConfig { name: "$R3E".to_string(), size: 39320, event: None }
+        ], "RaceRoom Racing Expierence"),
+        _ => return None
+    };
+
+    Some(preset)
+}
+
+/// The name of the game, if there is a preset for it
+pub fn get_preset_game_name(game_id: &str) -> Option<&'static str> {
+    get_preset_maps(game_id).map(|(_, game)| game)
+}
+
+/// The `PRESET_VERSION`, to pass to `install::manual_ensure_config` together with the preset
+pub fn get_preset_version() -> ToolVersion {
+    PRESET_VERSION.parse().unwrap_or_default()
+}
+
+/// Returns the preset for this game (by Steam AppId), None if there is none.
+///
+/// It is stamped with the `PRESET_VERSION` (also named in the notes), so it is only replaced
+/// by newer versions (see `install::manual_ensure_config`)
+pub fn get_preset(game_id: &str) -> Option<GameBridgeConfig> {
+    let (mmaps, game) = get_preset_maps(game_id)?;
+    let version = get_preset_version();
+
+    let conf = GameBridgeConfig::default().with_memory_maps(mmaps)
+        .with_tool_version(version)
+        .with_notes(format!("Datalink v{version} default config for {game}, do Not modify this file (you can copy it to create your own, or use the user section). Set enabled to false or delete this file to disable it"));
+
+    Some(conf)
+}
@@ -0,0 +1,122 @@
+//! Journal of the registry changes that are reverted once the session is over.
+//!
+//! The bridge changes the registry itself, this only records the previous values. Each one is
+//! written to a journal in the config folder before the change is made (like the overlay journal),
+//! so if the bridge was killed the next start can still revert them.
+
+use std::{fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::action_error;
+
+/// Not ending in json, so it is not read as a config
+const JOURNAL_FILE: &str = "registry.journal";
+
+/// State of a registry value before it was changed
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct PreviousValue {
+    /// Full key, including the root (like `HKCU\Software\Tool`)
+    pub key: String,
+    pub name: String,
+    /// REG_* type and data, None if the value did not exist
+    pub value: Option<(u32, Vec<u8>)>,
+    /// If the key was created by the change, it is deleted again (if empty)
+    pub created_key: bool
+}
+
+/// The registry changes of this session that still have to be reverted
+#[derive(Debug, Default)]
+pub struct RegistryJournal {
+    /// Without a folder nothing is written, so the changes are only reverted by this session
+    folder: Option<PathBuf>,
+    entries: Vec<PreviousValue>
+}
+
+impl RegistryJournal {
+    /// Opens the journal in the given folder (the config folder).
+    ///
+    /// Changes a previous session did not revert (so it crashed) are read back, `take_all` returns
+    /// them for reverting
+    pub fn open(folder: Option<PathBuf>) -> (Self, Result<(), String>) {
+        let mut journal = Self { folder, entries: Vec::new() };
+
+        let path = match journal.folder.as_ref() {
+            Some(folder) => folder.join(JOURNAL_FILE),
+            None => return (journal, Ok(()))
+        };
+        if !path.exists() {
+            return (journal, Ok(()));
+        }
+
+        match fs::read(path.as_path()).map_err(|e| e.to_string()).and_then(|data| serde_json::from_slice::<Vec<PreviousValue>>(&data).map_err(|e| e.to_string())) {
+            Ok(entries) => {
+                journal.entries = entries;
+                (journal, Ok(()))
+            },
+            Err(e) => (journal, Err(format!("Unable to read registry journal '{}': {e}", path.display())))
+        }
+    }
+
+    /// If there are no changes to revert
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Records the previous value, call this before changing it.
+    ///
+    /// If the journal can't be written the change should not be made
+    pub fn record(&mut self, previous: PreviousValue) -> io::Result<()> {
+        self.entries.push(previous);
+        if let Err(e) = self.write_journal() {
+            self.entries.pop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Drops the last recorded value, for when the change failed
+    pub fn forget_last(&mut self) {
+        if self.entries.pop().is_some() {
+            let _ = self.write_journal();
+        }
+    }
+
+    /// Takes all changes to revert, in the order they were made (so revert them in reverse).
+    ///
+    /// The journal stays until `finish` is called
+    pub fn take_all(&mut self) -> Vec<PreviousValue> {
+        std::mem::take(&mut self.entries)
+    }
+
+    /// Keeps the changes that failed to revert (to retry them later), and removes the journal
+    /// if there are none
+    pub fn finish(&mut self, failed: Vec<PreviousValue>) -> io::Result<()> {
+        self.entries = failed;
+
+        if self.entries.is_empty() {
+            if let Some(folder) = self.folder.as_ref() {
+                let _ = fs::remove_file(folder.join(JOURNAL_FILE));
+            }
+            Ok(())
+        } else {
+            self.write_journal()
+        }
+    }
+
+    fn write_journal(&self) -> io::Result<()> {
+        let folder = match self.folder.as_ref() {
+            Some(folder) => folder,
+            None => return Ok(())
+        };
+        fs::create_dir_all(folder.as_path()).map_err(|e| action_error(e, "Failed to create folder", folder.as_path()))?;
+
+        let journal = folder.join(JOURNAL_FILE);
+        let data = serde_json::to_vec_pretty(&self.entries).map_err(io::Error::other)?;
+
+        // Renaming over it, so a crash mid write does not leave a broken journal behind
+        let temp = journal.with_extension("journal.tmp");
+        fs::write(temp.as_path(), data).map_err(|e| action_error(e, "Failed to write registry journal", temp.as_path()))?;
+        fs::rename(temp.as_path(), journal.as_path()).map_err(|e| action_error(e, "Failed to write registry journal", journal.as_path()))
+    }
+}
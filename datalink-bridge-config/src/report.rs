@@ -0,0 +1,168 @@
+//! Report of where the entries of a merged config came from.
+//!
+//! The bridge merges every config in the folder, this lists for each map, app, post app, overlay
+//! and game id which files contributed it, what `sanitize` removed, and conflicts between configs.
+
+use std::{fmt::Display, path::Path};
+
+use crate::{error::{ConfigError, ConfigErrors}, GameBridgeConfig};
+
+/// Name of the file the report is written to (in the config folder, not `.json` so it is not read as a config)
+pub const REPORT_FILE: &str = "merge-report.txt";
+
+/// Name used for configs without a game_id override (the game id the game was launched with is used)
+pub const LAUNCHED_GAME_ID: &str = "<launched game id>";
+
+/// The result of merging the configs of a folder
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    /// The config files merged, in the order they were merged
+    pub files: Vec<String>,
+    pub maps: Vec<ReportEntry>,
+    pub apps: Vec<ReportEntry>,
+    pub post_apps: Vec<ReportEntry>,
+    pub overlays: Vec<ReportEntry>,
+    pub game_ids: Vec<ReportEntry>,
+    /// Settings configs disagreed on (like same name maps with different sizes)
+    pub conflicts: Vec<String>,
+    /// Configs that were skipped, as they are not enabled
+    pub disabled: Vec<String>,
+    /// Entries removed by configs with a higher priority (or the user section of their own config)
+    pub removed: Vec<String>,
+    /// Unknown fields, configs written for a newer version
+    pub warnings: Vec<String>,
+    /// Files that could not be read
+    pub errors: Vec<String>
+}
+
+/// A single entry of the merged config
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReportEntry {
+    /// Map name, app (exec and args), overlay target or game id
+    pub name: String,
+    /// Files that contained this entry (a file is listed multiple times if it contained it multiple times)
+    pub sources: Vec<String>,
+    /// What was deduplicated or overridden
+    pub notes: Vec<String>,
+
+    /// The value every source had (map size, overlay source)
+    values: Vec<String>
+}
+
+impl MergeReport {
+    /// Records the entries of a config before it is merged
+    pub(crate) fn add_config(&mut self, file: &str, config: &GameBridgeConfig) {
+        self.files.push(file.to_string());
+
+        for map in config.maps.iter() {
+            entry(&mut self.maps, map.name.as_str(), file, map.size.to_string());
+        }
+        for app in config.apps.iter() {
+            entry(&mut self.apps, app.to_string().as_str(), file, String::new());
+        }
+        for app in config.post_apps.iter() {
+            entry(&mut self.post_apps, app.to_string().as_str(), file, String::new());
+        }
+        for overlay in config.overlays.iter() {
+            // Overlays are deduplicated case insensitive
+            let name = self.overlays.iter()
+                .find(|e| e.name.eq_ignore_ascii_case(overlay.get_target()))
+                .map(|e| e.name.clone())
+                .unwrap_or(overlay.get_target().to_string());
+            entry(&mut self.overlays, name.as_str(), file, overlay.get_source().to_string());
+        }
+        entry(&mut self.game_ids, config.get_name_override().map(|s| s.as_str()).unwrap_or(LAUNCHED_GAME_ID), file, String::new());
+    }
+
+    /// Notes what `sanitize` did to the merged (and sanitized) config, and collects the errors
+    pub(crate) fn finish(&mut self, merged: Option<&GameBridgeConfig>, errors: &ConfigErrors) {
+        for entry in self.maps.iter_mut().filter(|e| e.sources.len() > 1) {
+            let size = merged.and_then(|m| m.maps.iter().find(|map| map.name == entry.name)).map(|map| map.size);
+            let mut sizes = entry.values.clone();
+            sizes.sort();
+            sizes.dedup();
+
+            if sizes.len() > 1 {
+                let list = entry.values.iter().zip(entry.sources.iter()).map(|(size, file)| format!("{size} ({file})")).collect::<Vec<_>>().join(", ");
+                let used = size.map(|s| s.to_string()).unwrap_or("none".to_string());
+                self.conflicts.push(format!("Map {} has different sizes: {list}, using {used}", entry.name));
+                entry.notes.push(format!("kept the largest size {used}"));
+            } else {
+                entry.notes.push("deduplicated".to_string());
+            }
+        }
+
+        for entry in self.apps.iter_mut().chain(self.post_apps.iter_mut()).filter(|e| e.sources.len() > 1) {
+            entry.notes.push(format!("deduplicated, runs once instead of {} times", entry.sources.len()));
+        }
+
+        for entry in self.overlays.iter_mut().filter(|e| e.sources.len() > 1) {
+            let (first, file) = (entry.values[0].clone(), entry.sources[0].clone());
+            if entry.values.iter().any(|source| source != &first) {
+                self.conflicts.push(format!("Overlay {} has different sources, using {first} ({file})", entry.name));
+            }
+            entry.notes.push(format!("using {first} from {file}, the others are ignored"));
+        }
+
+        if self.game_ids.len() > 1 {
+            for entry in self.game_ids.iter_mut() {
+                entry.notes.push("notified together with the other game ids".to_string());
+            }
+        }
+
+        for error in errors.0.iter() {
+            match error {
+                ConfigError::Conflict { message } => self.conflicts.push(message.clone()),
+                error if error.is_warning() => self.warnings.push(error.to_string()),
+                error => self.errors.push(error.to_string())
+            }
+        }
+    }
+
+    /// Writes the report into the config folder (as `REPORT_FILE`)
+    pub fn write(&self, folder: &Path) -> Result<(), ConfigError> {
+        crate::lock::write_atomic(folder.join(REPORT_FILE).as_path(), self.to_string().as_bytes())
+    }
+}
+
+impl Display for MergeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Merged {} config(s): {}", self.files.len(), self.files.join(", "))?;
+
+        let sections = [("Maps", &self.maps), ("Apps", &self.apps), ("Post apps", &self.post_apps), ("Overlays", &self.overlays), ("Game ids", &self.game_ids)];
+        for (title, entries) in sections.into_iter().filter(|(_, entries)| !entries.is_empty()) {
+            writeln!(f, "{title}:")?;
+            for entry in entries {
+                write!(f, "  {} from {}", entry.name, entry.sources.join(", "))?;
+                if !entry.notes.is_empty() {
+                    write!(f, ": {}", entry.notes.join(", "))?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        for (title, list) in [("Disabled", &self.disabled), ("Removed", &self.removed), ("Conflicts", &self.conflicts), ("Warnings", &self.warnings), ("Errors", &self.errors)] {
+            if !list.is_empty() {
+                writeln!(f, "{title}:")?;
+                for item in list {
+                    writeln!(f, "  {item}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn entry(list: &mut Vec<ReportEntry>, name: &str, file: &str, value: String) {
+    let entry = match list.iter_mut().position(|e| e.name == name) {
+        Some(pos) => &mut list[pos],
+        None => {
+            list.push(ReportEntry { name: name.to_string(), ..Default::default() });
+            list.last_mut().expect("We just pushed it")
+        }
+    };
+
+    entry.sources.push(file.to_string());
+    entry.values.push(value);
+}
@@ -0,0 +1,155 @@
+//! Versioning of the config format, and upgrading configs written for older versions.
+//!
+//! Every config carries a `schema_version` (configs from before it was introduced are version 0).
+//! When reading, older documents are migrated step by step to `SCHEMA_VERSION` before they are
+//! deserialized, and fields the bridge does not know are reported as warnings.
+
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::{error::ConfigError, format::{line_column, strip_jsonc, ConfigFormat}, App, GameBridgeConfig};
+
+/// The version of the config format written by this crate
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Field editors use to find the schema, allowed in every config
+pub const SCHEMA_KEY: &str = "$schema";
+
+/// Upgrades a document from version n (the index) to n+1
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [
+    migrate_v0
+];
+
+/// Version 0 is every config written before `schema_version` existed.
+///
+/// Back then a `root_mount_point` of Z was the same as not setting it, now it overrides the drive
+/// detected for the prefix, so it is dropped to keep using the detected one
+fn migrate_v0(config: &mut Map<String, Value>) -> Result<(), String> {
+    let default_root = config.get("root_mount_point")
+        .and_then(|drive| drive.as_str())
+        .is_some_and(|drive| drive.eq_ignore_ascii_case("z"));
+    if default_root {
+        config.remove("root_mount_point");
+    }
+
+    Ok(())
+}
+
+/// Parses a config, migrating it if it was written for an older version.
+///
+/// The format is picked by the extension of the path (json with comments, or toml), json if unknown.
+///
+/// Returns the config together with warnings (unknown fields, or a config written for a newer
+/// version), which do not stop the config from being used
+pub fn parse_config(path: &Path, content: &[u8]) -> Result<(GameBridgeConfig, Vec<ConfigError>), ConfigError> {
+    let text = std::str::from_utf8(content).map_err(|e| {
+        let (line, column) = line_column(&String::from_utf8_lossy(content), e.valid_up_to());
+        ConfigError::Parse { path: path.to_path_buf(), line, column, message: e.to_string() }
+    })?;
+    let format = ConfigFormat::from_path(path).unwrap_or_default();
+    let text = match format {
+        ConfigFormat::Json => strip_jsonc(text),
+        ConfigFormat::Toml => text.to_string()
+    };
+
+    let mut value: Value = match format {
+        ConfigFormat::Json => serde_json::from_str(&text).map_err(|e| ConfigError::parse(path, e))?,
+        ConfigFormat::Toml => toml::from_str(&text).map_err(|e| ConfigError::parse_toml(path, &text, e))?
+    };
+    let document = value.as_object_mut().ok_or(ConfigError::Schema { path: path.to_path_buf(), message: "Config is not an object".to_string() })?;
+
+    let mut warnings = Vec::new();
+    let version = match document.get("schema_version") {
+        None => 0,
+        Some(version) => version.as_u64().and_then(|v| u32::try_from(v).ok())
+            .ok_or(ConfigError::Schema { path: path.to_path_buf(), message: format!("Invalid schema_version {version}") })?
+    };
+
+    let migrated = version < SCHEMA_VERSION;
+    if migrated {
+        for migration in MIGRATIONS[version as usize..].iter() {
+            migration(document).map_err(|message| ConfigError::Schema { path: path.to_path_buf(), message })?;
+        }
+        document.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+    } else if version > SCHEMA_VERSION {
+        warnings.push(ConfigError::NewerSchema { path: path.to_path_buf(), version });
+    }
+
+    // Apps are untagged (so they could also be actions), so their fields are only checked here
+    for list in ["apps", "post_apps"] {
+        for (index, item) in document.get(list).and_then(|l| l.as_array()).into_iter().flatten().enumerate() {
+            if item.get("type").is_none() {
+                let _ = serde_ignored::deserialize(item, |field| {
+                    warnings.push(ConfigError::UnknownField { path: path.to_path_buf(), field: format!("{list}[{index}].{field}") });
+                }) as Result<App, _>;
+            }
+        }
+    }
+
+    let mut unknown = |field: serde_ignored::Path| {
+        let field = field.to_string();
+        if field != SCHEMA_KEY {
+            warnings.push(ConfigError::UnknownField { path: path.to_path_buf(), field });
+        }
+    };
+    let config = if migrated {
+        // The error has no position in the migrated document, but most likely the original text
+        // has the same problem, so we try to locate it there
+        serde_ignored::deserialize(value, &mut unknown).map_err(|e| match format {
+            ConfigFormat::Json => ConfigError::parse(path, serde_json::from_str::<GameBridgeConfig>(&text).err().unwrap_or(e)),
+            ConfigFormat::Toml => toml::from_str::<GameBridgeConfig>(&text).err()
+                .map(|located| ConfigError::parse_toml(path, &text, located))
+                .unwrap_or(ConfigError::parse(path, e))
+        })?
+    } else {
+        // Deserializing from the text keeps the line and column for errors
+        match format {
+            ConfigFormat::Json => {
+                let mut deserializer = serde_json::Deserializer::from_str(&text);
+                serde_ignored::deserialize(&mut deserializer, &mut unknown).map_err(|e| ConfigError::parse(path, e))?
+            },
+            ConfigFormat::Toml => {
+                let deserializer = toml::Deserializer::parse(&text).map_err(|e| ConfigError::parse_toml(path, &text, e))?;
+                serde_ignored::deserialize(deserializer, &mut unknown).map_err(|e| ConfigError::parse_toml(path, &text, e))?
+            }
+        }
+    };
+
+    Ok((config, warnings))
+}
+
+/// JSON Schema of the config, as published in `schema/datalink-config.schema.json`
+///
+/// Unlike the bridge the schema does not allow unknown fields, so editors point out typos
+#[cfg(feature = "schema")]
+pub fn json_schema() -> Value {
+    // Only plain objects, for objects combined with subschemas (flattened enums) the allowed
+    // fields are split across them
+    fn deny_unknown(value: &mut Value) {
+        const COMBINED: [&str; 3] = ["allOf", "anyOf", "oneOf"];
+        match value {
+            Value::Object(map) => {
+                let has_properties = map.contains_key("properties");
+                let combined = COMBINED.iter().any(|key| map.contains_key(*key));
+                if has_properties && !combined && !map.contains_key("additionalProperties") {
+                    map.insert("additionalProperties".to_string(), Value::Bool(false));
+                }
+                map.iter_mut()
+                    .filter(|(key, _)| !(has_properties && COMBINED.contains(&key.as_str())))
+                    .for_each(|(_, value)| deny_unknown(value));
+            },
+            Value::Array(list) => list.iter_mut().for_each(deny_unknown),
+            _ => ()
+        }
+    }
+
+    let mut schema = schemars::schema_for!(GameBridgeConfig).to_value();
+    deny_unknown(&mut schema);
+    if let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+        properties.insert(SCHEMA_KEY.to_string(), serde_json::json!({ "type": "string", "description": "Path or url of this schema, for editors" }));
+    }
+    schema
+}
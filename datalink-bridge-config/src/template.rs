@@ -0,0 +1,107 @@
+//! Placeholders in paths and args, resolved by the bridge before apps and actions are run.
+//!
+//! `{name}` is replaced with the value set for it (e.g. `{game_dir}`), `%NAME%` with the env
+//! variable of the same name (e.g. `%APPDATA%`). Only names made of letters, digits and `_` are
+//! treated as placeholders, so json (or a single `%`) in args is left alone. Unknown placeholders
+//! and env variables that are not set are kept as they are, like cmd does, as they might be meant
+//! for the app itself, but they are reported (they might just as well be a typo).
+
+use std::collections::BTreeMap;
+
+/// Placeholders every session provides
+pub const GAME_ID: &str = "game_id";
+pub const GAME_DIR: &str = "game_dir";
+pub const GAME_EXE: &str = "game_exe";
+pub const CONFIG_DIR: &str = "config_dir";
+pub const SHM_DIR: &str = "shm_dir";
+pub const SESSION_ID: &str = "session_id";
+
+const PLACEHOLDERS: [&str; 6] = [GAME_ID, GAME_DIR, GAME_EXE, CONFIG_DIR, SHM_DIR, SESSION_ID];
+
+/// If the text starts with one of the placeholders every session provides (e.g. `{game_dir}\tool.exe`)
+pub fn starts_with_placeholder(text: &str) -> bool {
+    text.strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .is_some_and(|(name, _)| PLACEHOLDERS.contains(&name))
+}
+
+/// The values placeholders are replaced with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateVars {
+    values: BTreeMap<String, String>
+}
+
+impl TemplateVars {
+    /// Sets the value for `{name}`
+    pub fn with(mut self, name: &str, value: String) -> Self {
+        self.values.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|v| v.as_str())
+    }
+
+    /// Replaces all known placeholders and env variables that are set
+    pub fn expand(&self, text: &str) -> String {
+        self.expand_reporting(text, &mut Vec::new())
+    }
+
+    /// Expands the value in place, adding unknown placeholders and env variables that are not set
+    /// to `unknown` (each only once)
+    pub fn expand_in(&self, value: &mut String, unknown: &mut Vec<String>) {
+        *value = self.expand_reporting(value.as_str(), unknown);
+    }
+
+    fn expand_reporting(&self, text: &str, unknown: &mut Vec<String>) -> String {
+        let mut res = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(pos) = rest.find(['{', '%']) {
+            res.push_str(&rest[..pos]);
+            let open = rest[pos..].chars().next().expect("We found it");
+            let close = if open == '{' { '}' } else { '%' };
+            let after = &rest[pos + 1..];
+
+            let name = after.find(close).map(|end| &after[..end]).filter(|name| is_name(name, open == '%'));
+            let value = name.and_then(|name| if open == '{' {
+                self.values.get(name).cloned()
+            } else {
+                std::env::var(name).ok()
+            });
+
+            match (name, value) {
+                (Some(name), Some(value)) => {
+                    res.push_str(value.as_str());
+                    rest = &after[name.len() + 1..];
+                },
+                (name, _) => {
+                    // Not a placeholder (or one we don't know), so we keep it
+                    if let Some(name) = name {
+                        let message = if open == '{' {
+                            format!("Unknown placeholder {{{name}}} in '{text}'")
+                        } else {
+                            format!("Env variable %{name}% in '{text}' is not set")
+                        };
+                        if !unknown.contains(&message) {
+                            unknown.push(message);
+                        }
+                    }
+
+                    res.push(open);
+                    rest = after;
+                }
+            }
+        }
+        res.push_str(rest);
+
+        res
+    }
+}
+
+/// Env variables can't start with a digit (so `%1%` is left alone), but can contain brackets
+/// (like `%ProgramFiles(x86)%`)
+fn is_name(name: &str, env: bool) -> bool {
+    let valid_start = name.chars().next().is_some_and(|c| !(env && c.is_ascii_digit()));
+    valid_start && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || (env && (c == '(' || c == ')')))
+}
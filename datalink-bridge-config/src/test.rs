@@ -1,7 +1,36 @@
-use crate::{App, GameBridgeConfig, MemMapConfig};
+use std::{collections::BTreeMap, ops::Deref, path::PathBuf};
+
+use crate::{error::ConfigError, format::ConfigFormat, install::{self, EnsureOutcome, ToolVersion}, lock::FolderLock, schema, ConfigRemovals, UserEdits, overlay::OverlaySession, patch, path::{self, ConfigPath}, policy::Policy, presets, registry::{PreviousValue, RegistryJournal}, template::{self, TemplateVars}, Action, App, AppContainer, GameBridgeConfig, MemMapConfig, Overlay, OverlayMode, ProcessTracking, RegistryRoot, RegistryValue, RestartPolicy, RunCondition, ShutdownPolicy, StartTrigger, WindowMode};
 
 const GAME_ID:u32 = 2420510;
 
+/// Temp folder for a test, removed again once dropped (so also if the test fails)
+struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Creates the empty folder, removing leftovers of an earlier run
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("datalink-{name}-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(path.as_path());
+        std::fs::create_dir_all(path.as_path()).expect("Failed to create test folder");
+        Self(path)
+    }
+}
+
+impl Deref for TestDir {
+    type Target = PathBuf;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(self.0.as_path());
+    }
+}
+
 #[test]
 #[cfg(feature = "proton")]
 pub fn find_path() {
@@ -18,10 +47,10 @@ pub fn find_path() {
 #[test]
 pub fn sanitize_game_mappings_conf() {
     let mut sample = GameBridgeConfig::default().with_memory_maps(vec![
-        MemMapConfig { name: "test".to_string(), size: 512 },
-        MemMapConfig { name: "hello".to_string(), size: 361 },
-        MemMapConfig { name: "Test".to_string(), size: 2048 },
-        MemMapConfig { name: "test".to_string(), size: 1000 }
+        MemMapConfig { name: "test".to_string(), size: 512, event: None },
+        MemMapConfig { name: "hello".to_string(), size: 361, event: None },
+        MemMapConfig { name: "Test".to_string(), size: 2048, event: None },
+        MemMapConfig { name: "test".to_string(), size: 1000, event: None }
     ]);
 
     sample.sanitize();
@@ -36,23 +65,1067 @@ pub fn sanitize_game_mappings_conf() {
     assert_eq!(sample.maps[2].size, 361, "Unexpected Size for row 3, Full Map is: {:?}", sample.maps);
 }
 
+#[test]
+pub fn sanitize_keeps_map_event() {
+    let mut sample = GameBridgeConfig::default().with_memory_maps(vec![
+        MemMapConfig { name: "test".to_string(), size: 512, event: Some("test_event".to_string()) },
+        MemMapConfig { name: "test".to_string(), size: 1000, event: None },
+    ]);
+
+    sample.sanitize();
+
+    assert_eq!(sample.maps.len(), 1, "Did not remove the expected amount of lines, Map is: {:?}", sample.maps);
+    assert_eq!(sample.maps[0].size, 1000, "Unexpected Size, Full Map is: {:?}", sample.maps);
+    assert_eq!(sample.maps[0].event.as_deref(), Some("test_event"), "Event got lost, Full Map is: {:?}", sample.maps);
+}
+
+/// Unwraps the App out of the container for the asserts
+fn as_app(container: &AppContainer) -> &App {
+    match container {
+        AppContainer::App(app) => app,
+        _ => panic!("Expected an App, got: {:?}", container)
+    }
+}
+
 #[test]
 pub fn sanitize_game_apps_conf() {
     let mut sample = GameBridgeConfig::default().with_autolaunch_apps(vec![
-        App::new("C:\\users\\steamuser\\Documents\\test.exe".to_string()).unwrap().with_args(vec!["--".to_string()]),
-        App::new("C:\\users\\steamuser\\Documents\\test.exe".to_string()).unwrap(),
-        App::new("C:\\users\\steamuser\\Documents\\t.exe".to_string()).unwrap(),
-        App::new("C:\\users\\steamuser\\Documents\\test.exe".to_string()).unwrap().with_args(vec!["--".to_string()]),
+        App::new("C:\\users\\steamuser\\Documents\\test.exe".to_string()).unwrap().with_args(vec!["--".to_string()]).into(),
+        App::new("C:\\users\\steamuser\\Documents\\test.exe".to_string()).unwrap().into(),
+        App::new("C:\\users\\steamuser\\Documents\\t.exe".to_string()).unwrap().into(),
+        App::new("C:\\users\\steamuser\\Documents\\test.exe".to_string()).unwrap().with_args(vec!["--".to_string()]).into(),
     ]);
 
     sample.sanitize();
 
     assert_eq!(sample.apps.len(), 3, "Did not remove the expected amount of lines, apps is: {:?}", sample.apps);
 
-    assert_eq!(sample.apps[0].get_exec(), "C:\\users\\steamuser\\Documents\\test.exe", "Unexpected Name for row 1, Full apps is: {:?}", sample.apps);
-    assert_eq!(sample.apps[0].get_args_as_ref().len(), 1, "Unexpected Size for row 1, Full apps is: {:?}", sample.apps);
-    assert_eq!(sample.apps[1].get_exec(), "C:\\users\\steamuser\\Documents\\test.exe", "Unexpected Name for row 2, Full apps is: {:?}", sample.apps);
-    assert_eq!(sample.apps[1].get_args_as_ref().len(), 0, "Unexpected Size for row 2, Full apps is: {:?}", sample.apps);
-    assert_eq!(sample.apps[2].get_exec(), "C:\\users\\steamuser\\Documents\\t.exe", "Unexpected Name for row 3, Full apps is: {:?}", sample.apps);
-    assert_eq!(sample.apps[2].get_args_as_ref().len(), 0, "Unexpected Size for row 3, Full apps is: {:?}", sample.apps);
+    assert_eq!(as_app(&sample.apps[0]).get_exec(), "C:\\users\\steamuser\\Documents\\test.exe", "Unexpected Name for row 1, Full apps is: {:?}", sample.apps);
+    assert_eq!(as_app(&sample.apps[0]).get_args_as_ref().len(), 1, "Unexpected Size for row 1, Full apps is: {:?}", sample.apps);
+    assert_eq!(as_app(&sample.apps[1]).get_exec(), "C:\\users\\steamuser\\Documents\\test.exe", "Unexpected Name for row 2, Full apps is: {:?}", sample.apps);
+    assert_eq!(as_app(&sample.apps[1]).get_args_as_ref().len(), 0, "Unexpected Size for row 2, Full apps is: {:?}", sample.apps);
+    assert_eq!(as_app(&sample.apps[2]).get_exec(), "C:\\users\\steamuser\\Documents\\t.exe", "Unexpected Name for row 3, Full apps is: {:?}", sample.apps);
+    assert_eq!(as_app(&sample.apps[2]).get_args_as_ref().len(), 0, "Unexpected Size for row 3, Full apps is: {:?}", sample.apps);
+}
+
+#[test]
+pub fn process_tracking_executables() {
+    let tracking = ProcessTracking { executables: vec!["AC2-Win64-*.exe".to_string(), "acc?.exe".to_string()], ..Default::default() };
+
+    assert!(tracking.matches_executable("AC2-Win64-Shipping.exe"), "Star pattern did not match");
+    assert!(tracking.matches_executable("ac2-win64-shipping.EXE"), "Matching is not case insensitive");
+    assert!(tracking.matches_executable("acc2.exe"), "Question mark pattern did not match");
+    assert!(!tracking.matches_executable("acc.exe"), "Question mark matched no character");
+    assert!(!tracking.matches_executable("CrashReporter.exe"), "Unrelated exe matched");
+
+    assert!(ProcessTracking::default().matches_executable("anything.exe"), "Empty rules have to match everything");
+}
+
+#[test]
+pub fn process_tracking_merge() {
+    let mut tracking = ProcessTracking { executables: vec!["game.exe".to_string()], grace_period_ms: Some(1000), ..Default::default() };
+    tracking.merge(ProcessTracking { executables: vec!["GAME.exe".to_string()], grace_period_ms: Some(3000), poll_interval_ms: Some(500), ..Default::default() });
+    tracking.sanitize();
+
+    assert_eq!(tracking.executables.len(), 1, "Dublicate executable not removed: {:?}", tracking);
+    assert_eq!(tracking.grace_period_ms, Some(3000), "Did not pick the longest grace period: {:?}", tracking);
+    assert_eq!(tracking.poll_interval_ms, Some(500), "Did not pick the set poll interval: {:?}", tracking);
+}
+
+#[test]
+pub fn process_tracking_poll_interval() {
+    assert_eq!(ProcessTracking::default().get_poll_interval(), std::time::Duration::from_secs(5));
+    assert_eq!(ProcessTracking { poll_interval_ms: Some(500), ..Default::default() }.get_poll_interval(), std::time::Duration::from_millis(500));
+
+    // A 0 (which wins when merging) must not make the bridge busy loop
+    let mut tracking = ProcessTracking { poll_interval_ms: Some(1000), ..Default::default() };
+    tracking.merge(ProcessTracking { poll_interval_ms: Some(0), ..Default::default() });
+    assert_eq!(tracking.get_poll_interval(), std::time::Duration::from_millis(100));
+}
+
+#[test]
+pub fn start_trigger_serde() {
+    let config: GameBridgeConfig = serde_json::from_str(r#"{ "start_trigger": { "type": "MapData", "map": "acpmf_physics" } }"#).expect("Failed to parse config");
+    assert_eq!(config.start_trigger, StartTrigger::MapData { map: "acpmf_physics".to_string() });
+
+    let config: GameBridgeConfig = serde_json::from_str("{}").expect("Failed to parse config");
+    assert_eq!(config.start_trigger, StartTrigger::Immediately);
+
+    let text = serde_json::to_string(&config).expect("Failed to serialize config");
+    assert!(!text.contains("start_trigger"), "Default trigger should not be serialized: {}", text);
+}
+
+#[test]
+pub fn start_trigger_process_requires_executables() {
+    let folder = TestDir::new("trigger");
+
+    // Only matching by the game folder, which includes the launcher
+    std::fs::write(folder.join("a.json"), r#"{ "start_trigger": { "type": "Process" } }"#).expect("Failed to write");
+    let (res, err, _) = crate::manual_read_configs_from_folder_with_report(folder.as_path(), None);
+    assert_eq!(res.map(|(config, _)| config.start_trigger), Some(StartTrigger::Immediately));
+    assert!(err.is_err_and(|e| e.to_string().contains("requires executables")));
+
+    std::fs::write(folder.join("b.json"), r#"{ "process_tracking": { "executables": ["AC2-Win64-Shipping.exe"] } }"#).expect("Failed to write");
+    let (res, err, _) = crate::manual_read_configs_from_folder_with_report(folder.as_path(), None);
+    assert_eq!(res.map(|(config, _)| config.start_trigger), Some(StartTrigger::Process));
+    assert!(err.is_ok());
+}
+
+#[test]
+pub fn app_start_conditions_serde() {
+    let apps: Vec<AppContainer> = serde_json::from_str(r#"[
+        {
+            "path": "C:\\tools\\dashboard.exe",
+            "args": [],
+            "delay_ms": 500,
+            "wait_for_process": "server*.exe",
+            "wait_for_map_data": "acpmf_physics",
+            "timeout_ms": 60000,
+            "run_if": { "type": "Not", "condition": { "type": "OverrideActive" } }
+        },
+        {
+            "path": "C:\\tools\\server.exe",
+            "args": []
+        }
+    ]"#).expect("Failed to parse apps");
+
+    let app = as_app(&apps[0]);
+    assert!(app.has_start_conditions(), "Start conditions were not parsed: {:?}", app);
+    assert_eq!(app.get_delay(), Some(std::time::Duration::from_millis(500)));
+    assert_eq!(app.get_wait_for_process(), Some("server*.exe"));
+    assert_eq!(app.get_wait_for_file(), None);
+    assert_eq!(app.get_wait_for_map_data(), Some("acpmf_physics"));
+    assert_eq!(app.get_timeout(), Some(std::time::Duration::from_secs(60)));
+    assert_eq!(app.get_run_if(), Some(&RunCondition::Not { condition: Box::new(RunCondition::OverrideActive) }));
+
+    let app = as_app(&apps[1]);
+    assert!(!app.has_start_conditions(), "App without conditions has some: {:?}", app);
+
+    let text = serde_json::to_string(app).expect("Failed to serialize app");
+    assert!(!text.contains("delay_ms") && !text.contains("run_if"), "Unset conditions should not be serialized: {}", text);
+}
+
+#[test]
+pub fn restart_policy_backoff() {
+    let app: App = serde_json::from_str(r#"{ "path": "C:\\tools\\server.exe", "args": [], "restart": { "type": "OnFailure", "max_retries": 3, "backoff_ms": 500 } }"#)
+        .expect("Failed to parse app");
+    let policy = app.get_restart();
+
+    assert_eq!(policy, &RestartPolicy::OnFailure { max_retries: 3, backoff_ms: 500 });
+    assert_eq!(policy.get_backoff(0), Some(std::time::Duration::from_millis(500)));
+    assert_eq!(policy.get_backoff(2), Some(std::time::Duration::from_millis(2000)));
+    assert_eq!(policy.get_backoff(3), None, "Restarted more often than max_retries");
+
+    assert_eq!(RestartPolicy::Never.get_backoff(0), None);
+}
+
+#[test]
+pub fn app_process_options_serde() {
+    let json = r#"{
+        "path": "C:\\tools\\server.exe",
+        "args": [],
+        "cwd": "C:\\tools",
+        "env": { "WINEDLLOVERRIDES": "dinput8=n,b", "SteamAppId": null },
+        "window": "NoWindow"
+    }"#;
+    let app: App = serde_json::from_str(json).expect("Failed to parse app");
+
+    assert_eq!(app.get_cwd(), Some("C:\\tools"));
+    assert_eq!(app.get_env().get("WINEDLLOVERRIDES"), Some(&Some("dinput8=n,b".to_string())));
+    assert_eq!(app.get_env().get("SteamAppId"), Some(&None));
+    assert_eq!(app.get_window(), WindowMode::NoWindow);
+    assert_ne!(app.get_window().get_creation_flags(), 0);
+
+    let built = App::new("C:\\tools\\server.exe".to_string()).expect("Valid path")
+        .with_cwd("C:\\tools".to_string())
+        .with_env("WINEDLLOVERRIDES".to_string(), "dinput8=n,b".to_string())
+        .with_env_removed("SteamAppId".to_string())
+        .with_window(WindowMode::NoWindow);
+    let written = serde_json::to_value(&built).expect("Failed to serialize app");
+    assert_eq!(written, serde_json::from_str::<serde_json::Value>(json).unwrap());
+
+    // Defaults are omitted
+    let plain = serde_json::to_string(&App::new("C:\\tools\\server.exe".to_string()).expect("Valid path")).unwrap();
+    assert!(!plain.contains("cwd") && !plain.contains("env") && !plain.contains("window"), "{plain}");
+}
+
+#[test]
+pub fn app_shutdown_policy_serde() {
+    let json = r#"{
+        "path": "C:\\tools\\server.exe",
+        "args": [],
+        "shutdown": {
+            "timeout_ms": 15000,
+            "kill_tree": true,
+            "stop_command": { "path": "C:\\tools\\server.exe", "args": [ "--stop" ] }
+        }
+    }"#;
+    let app: App = serde_json::from_str(json).expect("Failed to parse app");
+    let shutdown = app.get_shutdown();
+
+    assert_eq!(shutdown.get_timeout(), std::time::Duration::from_millis(15000));
+    assert!(shutdown.kills_tree());
+    assert!(!shutdown.is_detached());
+    assert_eq!(shutdown.get_stop_command().map(|c| c.get_args_as_ref().clone()), Some(vec!["--stop".to_string()]));
+
+    // Defaults
+    let plain = App::new("C:\\tools\\server.exe".to_string()).expect("Valid path");
+    assert_eq!(plain.get_shutdown().get_timeout(), std::time::Duration::from_millis(5000));
+    assert!(!serde_json::to_string(&plain).unwrap().contains("shutdown"));
+
+    let detached = plain.with_shutdown(ShutdownPolicy::default().with_detach(true));
+    let written = serde_json::to_string(&detached).expect("Failed to serialize app");
+    assert!(written.contains(r#""shutdown":{"detach":true}"#), "{written}");
+}
+
+#[test]
+pub fn app_wait_serde() {
+    let apps: Vec<AppContainer> = serde_json::from_str(r#"[
+        { "path": "C:\\tools\\backup.exe", "args": [], "wait": true, "wait_timeout_ms": 60000 },
+        { "path": "C:\\tools\\server.exe", "args": [] }
+    ]"#).expect("Failed to parse apps");
+
+    let backup = as_app(&apps[0]);
+    assert!(backup.waits());
+    assert_eq!(backup.get_wait_timeout(), std::time::Duration::from_millis(60000));
+
+    let server = as_app(&apps[1]);
+    assert!(!server.waits());
+    assert_eq!(server.get_wait_timeout(), std::time::Duration::from_secs(600));
+
+    let written = serde_json::to_string(&App::new("C:\\tools\\backup.exe".to_string()).expect("Valid path").with_wait(true))
+        .expect("Failed to serialize app");
+    assert!(written.contains(r#""wait":true"#) && !written.contains("wait_timeout_ms"), "{written}");
+}
+
+#[test]
+pub fn file_actions_serde() {
+    let apps: Vec<AppContainer> = serde_json::from_str(r#"[
+        { "type": "Delete", "file": "C:\\game\\old.cfg" },
+        { "type": "Delete", "file": "C:\\game\\logs\\*.log", "recursive": true },
+        { "type": "Copy", "from": "./configs/game.cfg", "to": "C:\\game\\cfg" },
+        { "type": "Move", "from": "C:\\game\\*.log", "to": "C:\\logs", "overwrite": true },
+        { "type": "Mkdir", "path": "C:\\logs" },
+        { "type": "Symlink", "target": "C:\\mods\\skin", "link": "C:\\game\\skins\\skin" }
+    ]"#).expect("Failed to parse actions");
+
+    let actions: Vec<Action> = apps.into_iter().map(|a| match a {
+        AppContainer::Action(action) => action,
+        AppContainer::App(app) => panic!("Action parsed as app {}", app.get_name())
+    }).collect();
+
+    assert_eq!(actions[0], Action::Delete { file: "C:\\game\\old.cfg".to_string(), recursive: false });
+    assert_eq!(actions[1], Action::Delete { file: "C:\\game\\logs\\*.log".to_string(), recursive: true });
+    assert_eq!(actions[2], Action::Copy { from: "./configs/game.cfg".to_string(), to: "C:\\game\\cfg".to_string(), overwrite: false });
+    assert_eq!(actions[3], Action::Move { from: "C:\\game\\*.log".to_string(), to: "C:\\logs".to_string(), overwrite: true });
+    assert_eq!(actions[4], Action::Mkdir { path: "C:\\logs".to_string() });
+    assert_eq!(actions[5], Action::Symlink { target: "C:\\mods\\skin".to_string(), link: "C:\\game\\skins\\skin".to_string() });
+
+    // Flags are omitted when not set, so old configs stay the same
+    assert_eq!(serde_json::to_string(&actions[0]).unwrap(), r#"{"type":"Delete","file":"C:\\game\\old.cfg"}"#);
+
+    // Actions are deduplicated by their description
+    let mut conf = GameBridgeConfig::default();
+    for action in actions.iter().chain(actions.iter()) {
+        conf.apps.push(action.clone().into());
+    }
+    conf.sanitize();
+    assert_eq!(conf.apps.len(), actions.len());
+}
+
+#[test]
+pub fn patch_json_keeps_rest() {
+    let content = "{\r\n\t\"updListenerPort\": 9000,\r\n\t\"connectionPassword\": \"asd\"\r\n}\r\n";
+
+    let mut set = BTreeMap::new();
+    set.insert("updListenerPort".to_string(), serde_json::json!(9996));
+    set.insert("/plugin.dll/ Enabled".to_string(), serde_json::json!(1));
+    set.insert("/a~1b".to_string(), serde_json::json!(true));
+
+    let patched = patch::patch_json(content, &set).expect("Failed to patch");
+    assert_eq!(patched, "{\r\n\t\"updListenerPort\": 9996,\r\n\t\"connectionPassword\": \"asd\",\r\n\t\"a/b\": true,\r\n\t\"plugin.dll\": {\r\n\t\t\" Enabled\": 1\r\n\t}\r\n}\r\n");
+
+    // New file
+    assert_eq!(patch::patch_json("", &set).expect("Failed to patch").lines().next(), Some("{"));
+    // Can't set into a value
+    set.insert("/updListenerPort/port".to_string(), serde_json::json!(1));
+    assert!(patch::patch_json(content, &set).is_err());
+}
+
+#[test]
+pub fn patch_ini_keeps_rest() {
+    let content = "; Telemetry settings\nglobal=1\n\n[UDP]\nEnabled = 0\nPort=5606\n\n[Other]\nvalue=a=b\n";
+
+    let mut set = BTreeMap::<String, BTreeMap<String, String>>::new();
+    set.entry("udp".to_string()).or_default().insert("enabled".to_string(), "1".to_string());
+    set.entry("udp".to_string()).or_default().insert("Frequency".to_string(), "60".to_string());
+    set.entry("".to_string()).or_default().insert("version".to_string(), "2".to_string());
+    set.entry("Shared Memory".to_string()).or_default().insert("Enabled".to_string(), "1".to_string());
+
+    let patched = patch::patch_ini(content, &set);
+    assert_eq!(patched, "; Telemetry settings\nglobal=1\nversion=2\n\n[UDP]\nEnabled = 1\nPort=5606\nFrequency=60\n\n[Other]\nvalue=a=b\n\n[Shared Memory]\nEnabled=1\n");
+
+    // Patching again changes nothing
+    assert_eq!(patch::patch_ini(patched.as_str(), &set), patched);
+
+    // Line endings are kept
+    assert!(patch::patch_ini(content.replace('\n', "\r\n").as_str(), &set).ends_with("[Shared Memory]\r\nEnabled=1\r\n"));
+}
+
+#[test]
+pub fn patch_xml_keeps_rest() {
+    let content = "<?xml version=\"1.0\"?>\n<!-- settings -->\n<Config version=\"1\">\n  <Telemetry enabled=\"false\" />\n  <Port>20777</Port>\n  <Empty></Empty>\n</Config>\n";
+
+    let mut set = BTreeMap::new();
+    set.insert("Config/Telemetry@enabled".to_string(), "true".to_string());
+    set.insert("Config/Telemetry@rate".to_string(), "60".to_string());
+    set.insert("Config/Port".to_string(), "20778".to_string());
+    set.insert("Config/Empty".to_string(), "a & b".to_string());
+    set.insert("Config/Udp/Host".to_string(), "127.0.0.1".to_string());
+
+    let patched = patch::patch_xml(content, &set).expect("Failed to patch");
+    assert_eq!(patched, "<?xml version=\"1.0\"?>\n<!-- settings -->\n<Config version=\"1\">\n  <Telemetry rate=\"60\" enabled=\"true\" />\n  <Port>20778</Port>\n  <Empty>a &amp; b</Empty>\n<Udp><Host>127.0.0.1</Host></Udp></Config>\n");
+
+    // New file
+    let created = patch::patch_xml("", &set).expect("Failed to create");
+    assert!(roxmltree::Document::parse(created.as_str()).is_ok(), "{created}");
+
+    // Wrong root
+    set.insert("Other/Port".to_string(), "1".to_string());
+    assert!(patch::patch_xml(content, &set).is_err());
+}
+
+#[test]
+pub fn patch_file_keeps_encoding() {
+    let text = "{\"a\": 1}";
+    let bytes = patch::TextEncoding::Utf16Le.encode(text);
+
+    assert_eq!(&bytes[..2], &[0xFF, 0xFE]);
+    assert_eq!(patch::TextEncoding::decode(&bytes), Ok((text.to_string(), patch::TextEncoding::Utf16Le)));
+    assert_eq!(patch::TextEncoding::decode(text.as_bytes()), Ok((text.to_string(), patch::TextEncoding::Utf8)));
+}
+
+#[test]
+pub fn patch_actions_serde() {
+    let action: Action = serde_json::from_str(r#"{
+        "type": "PatchJson",
+        "file": "C:\\users\\steamuser\\Documents\\Assetto Corsa Competizione\\Config\\broadcasting.json",
+        "set": { "updListenerPort": 9000 },
+        "encoding": "Utf16Le"
+    }"#).expect("Failed to parse action");
+
+    match &action {
+        Action::PatchJson { set, encoding, .. } => {
+            assert_eq!(set.get("updListenerPort"), Some(&serde_json::json!(9000)));
+            assert_eq!(encoding, &Some(patch::TextEncoding::Utf16Le));
+        },
+        _ => panic!("Wrong action parsed: {}", action.get_description())
+    }
+
+    let ini: Action = serde_json::from_str(r#"{ "type": "PatchIni", "file": "./game.ini", "set": { "UDP": { "Enabled": "1" } } }"#)
+        .expect("Failed to parse action");
+    assert!(!serde_json::to_string(&ini).unwrap().contains("encoding"));
+
+    let xml: Action = serde_json::from_str(r#"{ "type": "PatchXml", "file": "./game.xml", "set": { "Config/Port": "20777" } }"#)
+        .expect("Failed to parse action");
+    assert!(matches!(xml, Action::PatchXml { .. }));
+}
+
+#[test]
+pub fn registry_actions_serde() {
+    let actions: Vec<Action> = serde_json::from_str(r#"[
+        { "type": "RegSet", "key": "HKCU\\Software\\Tool", "name": "Port", "value_type": "Dword", "data": 9000, "restore": true },
+        { "type": "RegSet", "key": "HKEY_CURRENT_USER\\Software\\Tool", "value_type": "String", "data": "enabled" },
+        { "type": "RegDelete", "key": "HKLM\\Software\\Tool", "name": "Disabled" }
+    ]"#).expect("Failed to parse actions");
+
+    assert_eq!(actions[0], Action::RegSet { key: "HKCU\\Software\\Tool".to_string(), name: "Port".to_string(), value: RegistryValue::Dword(9000), restore: true });
+    assert_eq!(actions[1], Action::RegSet { key: "HKEY_CURRENT_USER\\Software\\Tool".to_string(), name: String::new(), value: RegistryValue::String("enabled".to_string()), restore: false });
+    assert_eq!(actions[2], Action::RegDelete { key: "HKLM\\Software\\Tool".to_string(), name: "Disabled".to_string(), restore: false });
+
+    let written = serde_json::to_string(&actions[0]).expect("Failed to serialize");
+    assert_eq!(written, r#"{"type":"RegSet","key":"HKCU\\Software\\Tool","name":"Port","value_type":"Dword","data":9000,"restore":true}"#);
+
+    // Also parsed as part of the apps
+    let container: AppContainer = serde_json::from_str(written.as_str()).expect("Failed to parse container");
+    assert!(matches!(container, AppContainer::Action(Action::RegSet { .. })));
+}
+
+#[test]
+pub fn registry_key_and_data() {
+    assert_eq!(RegistryRoot::split_key("HKCU\\Software\\Tool"), Some((RegistryRoot::CurrentUser, "Software\\Tool")));
+    assert_eq!(RegistryRoot::split_key("hkey_local_machine\\Software\\"), Some((RegistryRoot::LocalMachine, "Software")));
+    assert_eq!(RegistryRoot::split_key("HKCU\\"), None);
+    assert_eq!(RegistryRoot::split_key("Software\\Tool"), None);
+
+    assert_eq!(RegistryValue::Dword(1).to_bytes(), vec![1, 0, 0, 0]);
+    assert_eq!(RegistryValue::String("a".to_string()).to_bytes(), vec![b'a', 0, 0, 0]);
+    assert_eq!(RegistryValue::MultiString(vec!["a".to_string(), "b".to_string()]).to_bytes(), vec![b'a', 0, 0, 0, b'b', 0, 0, 0, 0, 0]);
+    assert_eq!(RegistryValue::Qword(2).get_type_id(), 11);
+}
+
+#[test]
+pub fn registry_actions_expand_templates() {
+    let vars = TemplateVars::default().with(template::GAME_ID, "365960".to_string()).with(template::GAME_DIR, "C:\\Game".to_string());
+
+    let mut action = Action::RegSet { key: "HKCU\\Software\\Tool\\{game_id}".to_string(), name: "Dir".to_string(), value: RegistryValue::String("{game_dir}".to_string()), restore: true };
+    action.expand_templates(&vars);
+    assert_eq!(action, Action::RegSet { key: "HKCU\\Software\\Tool\\365960".to_string(), name: "Dir".to_string(), value: RegistryValue::String("C:\\Game".to_string()), restore: true });
+
+    let mut value = RegistryValue::MultiString(vec!["{game_id}".to_string(), "%DATALINK_SURELY_NOT_SET%".to_string()]);
+    assert_eq!(value.expand_templates(&vars), vec!["Env variable %DATALINK_SURELY_NOT_SET% in '%DATALINK_SURELY_NOT_SET%' is not set".to_string()]);
+    assert_eq!(value, RegistryValue::MultiString(vec!["365960".to_string(), "%DATALINK_SURELY_NOT_SET%".to_string()]));
+
+    // Windows expands these itself
+    let mut value = RegistryValue::ExpandString("{game_dir}\\%USERNAME%".to_string());
+    value.expand_templates(&vars);
+    assert_eq!(value, RegistryValue::ExpandString("{game_dir}\\%USERNAME%".to_string()));
+
+    let mut action = Action::RegDelete { key: "HKCU\\Software\\{game_id}".to_string(), name: "{game_id}".to_string(), restore: false };
+    action.expand_templates(&vars);
+    assert_eq!(action, Action::RegDelete { key: "HKCU\\Software\\365960".to_string(), name: "365960".to_string(), restore: false });
+}
+
+#[test]
+pub fn registry_journal_replays() {
+    // Not created yet, that is up to the code under test
+    let dir = TestDir::new("registry");
+    let folder = dir.join("config");
+
+    let previous = |name: &str| PreviousValue { key: "HKCU\\Software\\Tool".to_string(), name: name.to_string(), value: Some((4, vec![1, 0, 0, 0])), created_key: false };
+
+    let (mut journal, res) = RegistryJournal::open(Some(folder.clone()));
+    assert!(res.is_ok());
+    assert!(journal.is_empty());
+    journal.record(previous("Port")).expect("Failed to record");
+    journal.record(previous("Failed")).expect("Failed to record");
+    journal.forget_last();
+
+    // Dropping it without restoring is what a crash looks like, the next one reads it back
+    drop(journal);
+    let (mut journal, res) = RegistryJournal::open(Some(folder.clone()));
+    assert!(res.is_ok());
+    assert_eq!(journal.take_all(), vec![previous("Port")]);
+
+    // Failed ones are kept for the next start
+    journal.finish(vec![previous("Port")]).expect("Failed to finish");
+    let (mut journal, _) = RegistryJournal::open(Some(folder.clone()));
+    assert!(!journal.is_empty());
+
+    let _ = journal.take_all();
+    journal.finish(Vec::new()).expect("Failed to finish");
+    assert_eq!(std::fs::read_dir(folder.as_path()).map(|d| d.count()).ok(), Some(0));
+
+    // Without a folder it only lives in memory
+    let (mut journal, _) = RegistryJournal::open(None);
+    journal.record(previous("Port")).expect("Failed to record");
+    assert_eq!(journal.take_all().len(), 1);
+}
+
+#[test]
+pub fn overlays_serde() {
+    let config: GameBridgeConfig = serde_json::from_str(r#"{
+        "overlays": [
+            { "target": "C:\\Games\\rFactor 2\\UserData\\Controller.JSON", "source": "/home/user/sim/Controller.JSON" },
+            { "target": "c:\\games\\rfactor 2\\userdata\\controller.json", "source": "/home/user/other.json" },
+            { "target": "C:\\Games\\rFactor 2\\Plugins\\Telemetry.dll", "source": "/home/user/sim/Telemetry.dll", "mode": "Symlink" }
+        ]
+    }"#).expect("Failed to parse config");
+
+    let mut config = config;
+    config.sanitize();
+
+    // Same target in a different case is dropped
+    assert_eq!(config.overlays.len(), 2);
+    assert_eq!(config.overlays[0].get_source(), "/home/user/sim/Controller.JSON");
+    assert_eq!(config.overlays[1].get_mode(), OverlayMode::Symlink);
+
+    let written = serde_json::to_string(&Overlay::new("a".to_string(), "b".to_string())).expect("Failed to serialize");
+    assert_eq!(written, r#"{"target":"a","source":"b"}"#);
+}
+
+#[test]
+pub fn overlay_session_restores() {
+    let dir = TestDir::new("overlay");
+    let config = dir.join("config");
+    let game = dir.join("game");
+    std::fs::create_dir_all(game.as_path()).expect("Failed to create test folder");
+
+    std::fs::write(game.join("settings.ini"), "original").expect("Failed to write");
+    std::fs::write(dir.join("replacement.ini"), "replaced").expect("Failed to write");
+
+    let (mut session, res) = OverlaySession::start(config.clone());
+    assert!(res.is_ok());
+    session.apply(dir.join("replacement.ini").as_path(), game.join("settings.ini").as_path(), OverlayMode::Copy).expect("Failed to apply");
+    session.apply(dir.join("replacement.ini").as_path(), game.join("new/added.ini").as_path(), OverlayMode::Copy).expect("Failed to apply");
+    assert!(session.apply(dir.join("missing.ini").as_path(), game.join("other.ini").as_path(), OverlayMode::Copy).is_err());
+
+    assert_eq!(std::fs::read_to_string(game.join("settings.ini")).ok().as_deref(), Some("replaced"));
+    assert!(game.join("new/added.ini").exists());
+
+    // Dropping the session without restoring is what a crash looks like, the next one restores it
+    drop(session);
+    let (session, res) = OverlaySession::start(config.clone());
+    assert!(res.is_ok());
+    assert!(session.is_empty());
+
+    assert_eq!(std::fs::read_to_string(game.join("settings.ini")).ok().as_deref(), Some("original"));
+    assert!(!game.join("new/added.ini").exists());
+    assert_eq!(std::fs::read_dir(config.as_path()).map(|d| d.count()).ok(), Some(0));
+
+    // And the regular way
+    let (mut session, _) = OverlaySession::start(config.clone());
+    session.apply(dir.join("replacement.ini").as_path(), game.join("settings.ini").as_path(), OverlayMode::Copy).expect("Failed to apply");
+    assert!(session.restore().is_ok());
+    assert_eq!(std::fs::read_to_string(game.join("settings.ini")).ok().as_deref(), Some("original"));
+}
+
+#[test]
+pub fn policy_allows_configs() {
+    let policy: Policy = serde_json::from_str(r#"{
+        "allowed_configs": ["datalink-*.json"],
+        "allowed_hashes": ["BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD"]
+    }"#).expect("Failed to parse policy");
+
+    assert!(policy.allows_config("Datalink-Default.json", b"{}"));
+    assert!(policy.allows_config("tool.json", b"abc"));
+    assert!(!policy.allows_config("tool.json", b"abcd"));
+    assert!(!Policy::default().allows_config("datalink-default.json", b"{}"));
+
+    // Without allowed_dirs every path is fine
+    assert!(policy.allows_path('Z', "C:\\Windows\\System32"));
+}
+
+#[test]
+pub fn policy_allows_paths() {
+    let policy = Policy::default()
+        .with_allowed_dir("C:\\Games\\rFactor 2\\".to_string())
+        .with_allowed_dir("/home/user/sim".to_string());
+
+    assert!(policy.allows_path('Z', "c:/games/rfactor 2/UserData/player.JSON"));
+    assert!(policy.allows_path('Z', "C:\\Games\\rFactor 2"));
+    assert!(policy.allows_path('Z', "/home/user/sim/logs/*.log"));
+    assert!(policy.allows_path('Z', "Z:\\home\\user\\sim\\a.txt"));
+
+    assert!(!policy.allows_path('Z', "C:\\Games\\rFactor 2\\..\\..\\Windows"));
+    assert!(!policy.allows_path('Z', "C:\\Games\\rFactor 20\\file"));
+    assert!(!policy.allows_path('Y', "Z:\\\\home\\\\user\\\\sim\\\\a.txt"));
+    assert!(!policy.allows_path('Z', "relative.txt"));
+}
+
+#[test]
+pub fn policy_filters_config() {
+    let dir = TestDir::new("policy");
+
+    let content = r#"{
+        "apps": [
+            { "path": "C:\\Games\\Sim\\server.exe", "args": [] },
+            { "type": "Delete", "file": "C:\\Games\\Sim\\log.txt" },
+            { "type": "Delete", "file": "C:\\Windows\\win.ini" }
+        ],
+        "overlays": [ { "target": "C:\\users\\steamuser\\file", "source": "/tmp/file" } ]
+    }"#;
+    std::fs::write(dir.join("trusted.json"), content).expect("Failed to write");
+    std::fs::write(dir.join("other.json"), content).expect("Failed to write");
+
+    let policy = Policy::default()
+        .with_allowed_config("trusted.json".to_string())
+        .with_allowed_dir("C:\\Games".to_string());
+
+    let mut trusted = policy.read_config(&dir.join("trusted.json")).expect("Failed to read");
+    assert_eq!(trusted.apps.len(), 3);
+    policy.filter_paths(&mut trusted);
+    assert_eq!(trusted.apps.len(), 2);
+    assert!(matches!(trusted.apps[1], AppContainer::Action(Action::Delete { .. })));
+    assert!(trusted.overlays.is_empty());
+
+    let other = policy.read_config(&dir.join("other.json")).expect("Failed to read");
+    assert!(other.apps.is_empty());
+
+    let report = policy.report(dir.as_path(), &TemplateVars::default());
+    assert!(report.contains("other.json (sha256 "));
+    assert!(report.contains("DELETE C:\\Windows\\win.ini: skipped, C:\\Windows\\win.ini is outside the allowed folders"));
+    assert_eq!(report.matches(": allowed").count(), 2);
+}
+
+#[test]
+pub fn policy_checks_app_paths() {
+    let policy = Policy::default()
+        .with_allowed_config("tool.json".to_string())
+        .with_allowed_dir("C:\\Games".to_string());
+    let check = |config: &str| {
+        let mut config: GameBridgeConfig = serde_json::from_str(config).expect("Failed to parse config");
+        policy.filter_paths(&mut config);
+        (config.apps.len(), config.overlays.len())
+    };
+
+    assert_eq!(check(r#"{ "apps": [ { "path": "C:\\Games\\tool.exe", "args": [], "cwd": "C:\\Games", "wait_for_file": "C:\\Games\\ready" } ] }"#), (1, 0));
+    assert_eq!(check(r#"{ "apps": [ { "path": "C:\\tools\\tool.exe", "args": [] } ] }"#), (0, 0));
+    assert_eq!(check(r#"{ "apps": [ { "path": "C:\\Games\\tool.exe", "args": [], "cwd": "C:\\Windows" } ] }"#), (0, 0));
+    assert_eq!(check(r#"{ "apps": [ { "path": "C:\\Games\\tool.exe", "args": [], "wait_for_file": "C:\\Games\\..\\ready" } ] }"#), (0, 0));
+    assert_eq!(check(r#"{ "apps": [ { "path": "C:\\Games\\tool.exe", "args": [],
+        "shutdown": { "stop_command": { "path": "C:\\Windows\\taskkill.exe", "args": [] } } } ] }"#), (0, 0));
+
+    // Overlays need both target and source within the allowed folders
+    assert_eq!(check(r#"{ "overlays": [ { "target": "C:\\Games\\file", "source": "C:\\Games\\mod\\file" } ] }"#), (0, 1));
+    assert_eq!(check(r#"{ "overlays": [ { "target": "C:\\Games\\file", "source": "/tmp/file" } ] }"#), (0, 0));
+}
+
+#[test]
+pub fn template_expands() {
+    let vars = TemplateVars::default()
+        .with(template::GAME_ID, "365960".to_string())
+        .with(template::GAME_DIR, "C:\\Games\\rFactor 2".to_string());
+
+    assert_eq!(vars.expand("{game_dir}\\Bin64\\rFactor2.exe"), "C:\\Games\\rFactor 2\\Bin64\\rFactor2.exe");
+    assert_eq!(vars.expand("--game={game_id}"), "--game=365960");
+
+    // Json, percentages and the like are not placeholders
+    assert_eq!(vars.expand(r#"{"port": 20777}"#), r#"{"port": 20777}"#);
+    assert_eq!(vars.expand("50% of %1%"), "50% of %1%");
+
+    // Unknown ones are kept, they might be meant for the app
+    assert_eq!(vars.expand("{game_path}\\file"), "{game_path}\\file");
+    assert_eq!(vars.expand("%DATALINK_SURELY_NOT_SET%;{game_id}"), "%DATALINK_SURELY_NOT_SET%;365960");
+    if let Ok(path) = std::env::var("PATH") {
+        assert_eq!(vars.expand("%PATH%;{game_dir}"), format!("{path};C:\\Games\\rFactor 2"));
+    }
+
+    // But reported, as they might as well be a typo
+    let mut unknown = Vec::new();
+    let mut text = "{game_dri}\\tool.exe".to_string();
+    vars.expand_in(&mut text, &mut unknown);
+    vars.expand_in(&mut "{game_dri}\\tool.exe".to_string(), &mut unknown);
+    vars.expand_in(&mut "%DATALINK_SURELY_NOT_SET%;50%;{game_id}".to_string(), &mut unknown);
+    assert_eq!(text, "{game_dri}\\tool.exe");
+    assert_eq!(unknown, vec![
+        "Unknown placeholder {game_dri} in '{game_dri}\\tool.exe'".to_string(),
+        "Env variable %DATALINK_SURELY_NOT_SET% in '%DATALINK_SURELY_NOT_SET%;50%;{game_id}' is not set".to_string()
+    ]);
+}
+
+#[test]
+pub fn config_expands_templates() {
+    let mut config: GameBridgeConfig = serde_json::from_str(r#"{
+        "apps": [
+            { "path": "{game_dir}\\tool.exe", "args": ["--id", "{game_id}"], "cwd": "{game_dir}" },
+            { "type": "Copy", "from": "{config_dir}\\a.ini", "to": "{game_dir}\\a.ini" },
+            { "path": "C:\\tool.exe", "args": ["{unknown}", "--log=%DATALINK_SURELY_NOT_SET%\\{game_id}.log"] }
+        ],
+        "overlays": [ { "target": "{game_dir}\\Controller.JSON", "source": "{config_dir}\\Controller.JSON" } ]
+    }"#).expect("Failed to parse config");
+
+    let vars = TemplateVars::default()
+        .with(template::GAME_ID, "365960".to_string())
+        .with(template::GAME_DIR, "C:\\Game".to_string())
+        .with(template::CONFIG_DIR, "C:\\Config".to_string());
+
+    let unknown = config.expand_templates(&vars);
+    assert_eq!(unknown, vec![
+        "C:\\tool.exe{unknown}--log=%DATALINK_SURELY_NOT_SET%\\365960.log: Unknown placeholder {unknown} in '{unknown}'".to_string(),
+        "C:\\tool.exe{unknown}--log=%DATALINK_SURELY_NOT_SET%\\365960.log: Env variable %DATALINK_SURELY_NOT_SET% in '--log=%DATALINK_SURELY_NOT_SET%\\{game_id}.log' is not set".to_string()
+    ]);
+
+    assert_eq!(config.apps.len(), 3);
+    assert_eq!(config.apps[0].to_string(), "C:\\Game\\tool.exe--id365960");
+    assert_eq!(config.apps[1].to_string(), "COPY C:\\Config\\a.ini C:\\Game\\a.ini");
+    assert_eq!(config.overlays[0].get_source(), "C:\\Config\\Controller.JSON");
+
+    // Args with an unrelated placeholder or env variable still launch, with them unchanged
+    assert_eq!(as_app(&config.apps[2]).get_args_as_ref(), &vec!["{unknown}".to_string(), "--log=%DATALINK_SURELY_NOT_SET%\\365960.log".to_string()]);
+}
+
+#[test]
+pub fn config_path_parse() {
+    let parse = |p: &str| p.parse::<ConfigPath>();
+
+    assert_eq!(parse("C:/Games/rFactor 2"), Ok(ConfigPath::Windows { drive: 'C', path: "Games\\rFactor 2".to_string() }));
+    assert_eq!(parse("/home/user/tool"), Ok(ConfigPath::Linux("/home/user/tool".to_string())));
+    assert_eq!(parse("%APPDATA%\\Tool"), Ok(ConfigPath::EnvRelative { var: "APPDATA".to_string(), path: "Tool".to_string() }));
+    assert_eq!(parse("./tools/server.exe"), Ok(ConfigPath::ConfigRelative("tools\\server.exe".to_string())));
+    assert!(parse("").is_err());
+    assert!(parse("\\\\server\\share").is_err());
+    assert!(parse("{game_dir}\\file").is_err());
+
+    for text in ["C:\\Games\\rFactor 2", "/home/user/tool", "%APPDATA%\\Tool", "%USERPROFILE%", "tools\\server.exe"] {
+        assert_eq!(parse(text).map(|p| p.to_string()), Ok(text.to_string()));
+    }
+
+    let config_dir = std::path::Path::new("C:\\users\\steamuser\\AppData\\Roaming\\Datalink");
+    assert_eq!(parse("/home/user/tool").ok().and_then(|p| p.to_windows('z', config_dir)), Some("Z:\\home\\user\\tool".to_string()));
+    assert_eq!(parse("c:\\Games").ok().and_then(|p| p.to_windows('Z', config_dir)), Some("C:\\Games".to_string()));
+
+    // Same rules for apps
+    assert!(App::new("%APPDATA%\\Tool\\tool.exe".to_string()).is_some());
+
+    // Known placeholders are only resolved by the bridge, the expanded path is checked then
+    assert!(App::new("{game_dir}\\tool.exe".to_string()).is_some());
+    assert!(App::new("{game_dri}\\tool.exe".to_string()).is_none());
+    let vars = TemplateVars::default().with(template::GAME_DIR, "C:\\Games\\rFactor 2".to_string());
+    assert!(parse(vars.expand("{game_dir}\\tool.exe").as_str()).is_ok());
+    assert!(parse(TemplateVars::default().expand("{game_dir}\\tool.exe").as_str()).is_err());
+}
+
+#[test]
+pub fn config_path_linux_side() {
+    let prefix = TestDir::new("prefix");
+    let roaming = prefix.join("drive_c/users/steamuser/AppData/Roaming");
+    std::fs::create_dir_all(roaming.join("Datalink")).expect("Failed to create test prefix");
+    std::fs::create_dir_all(prefix.join("drive_c/Games")).expect("Failed to create test prefix");
+    std::fs::create_dir_all(prefix.join("drive_c/users/Public")).expect("Failed to create test prefix");
+    std::fs::create_dir_all(prefix.join("dosdevices")).expect("Failed to create test prefix");
+    std::os::unix::fs::symlink("../drive_c", prefix.join("dosdevices/c:")).expect("Failed to link");
+    std::os::unix::fs::symlink("/", prefix.join("dosdevices/z:")).expect("Failed to link");
+    std::os::unix::fs::symlink("/dev/null", prefix.join("dosdevices/com1")).expect("Failed to link");
+
+    let drive_c = prefix.join("drive_c").canonicalize().expect("Exists");
+    let drives = path::drive_letters(prefix.as_path()).expect("Failed to read drives");
+    assert_eq!(drives, vec![('C', drive_c.clone()), ('Z', std::path::PathBuf::from("/"))]);
+
+    let to_linux = |p: &str| p.parse::<ConfigPath>().and_then(|p| p.to_linux(prefix.as_path()));
+    assert_eq!(to_linux("c:\\GAMES\\Sim\\sim.exe"), Ok(drive_c.join("Games/Sim/sim.exe")));
+    assert_eq!(to_linux("Z:\\home\\user"), Ok(std::path::PathBuf::from("/home/user")));
+    assert_eq!(to_linux("%APPDATA%\\Tool"), Ok(drive_c.join("users/steamuser/AppData/Roaming/Tool")));
+    assert_eq!(to_linux("tools\\server.exe"), Ok(drive_c.join("users/steamuser/AppData/Roaming/Datalink/tools/server.exe")));
+    assert!(to_linux("D:\\file").is_err());
+    assert!(to_linux("%SOMETHING%\\file").is_err());
+
+    assert_eq!(ConfigPath::from_linux(prefix.as_path(), drive_c.join("Games/Sim").as_path()).map(|p| p.to_string()), Ok("C:\\Games\\Sim".to_string()));
+    assert_eq!(ConfigPath::from_linux(prefix.as_path(), std::path::Path::new("/home/user")).map(|p| p.to_string()), Ok("Z:\\home\\user".to_string()));
+}
+
+#[test]
+pub fn detected_root_mount_point() {
+    // Unset in the config, so the detected drive is used
+    let mut config = GameBridgeConfig::default();
+    assert_eq!(config.apply_detected_root_mount_point('y'), None);
+    assert_eq!(config.get_root_mount_point(), 'Y');
+
+    // Set in the config, which still wins, but with a warning on mismatch
+    let mut config = GameBridgeConfig::default().with_override_root_mountpoint('L');
+    assert!(config.apply_detected_root_mount_point('Y').is_some());
+    assert_eq!(config.get_root_mount_point(), 'L');
+    assert_eq!(config.apply_detected_root_mount_point('l'), None);
+
+    // Setting Z explicitly is not the same as leaving it unset
+    let mut config: GameBridgeConfig = serde_json::from_str(r#"{ "root_mount_point": "Z" }"#).expect("Failed to parse config");
+    assert!(config.apply_detected_root_mount_point('Y').is_some());
+    assert_eq!(config.get_root_mount_point(), 'Z');
+    let json = serde_json::to_string(&GameBridgeConfig::default().with_override_root_mountpoint('Z')).expect("Failed to serialize config");
+    assert!(json.contains(r#""root_mount_point":"Z""#));
+    assert!(!serde_json::to_string(&GameBridgeConfig::default()).expect("Failed to serialize config").contains("root_mount_point"));
+
+    // Merging keeps the first letter set, even if a later config leaves it unset
+    let folder = TestDir::new("root");
+    std::fs::write(folder.join("a.json"), r#"{ "root_mount_point": "L" }"#).expect("Failed to write config");
+    std::fs::write(folder.join("b.json"), r#"{}"#).expect("Failed to write config");
+    std::fs::write(folder.join("c.json"), r#"{ "root_mount_point": "L" }"#).expect("Failed to write config");
+
+    let (res, err) = crate::manual_read_configs_from_folder(&folder);
+    assert!(err.is_ok());
+    assert_eq!(res.map(|(config, _)| config.get_root_mount_point()), Some('L'));
+
+    // An explicit Z conflicts with a different letter
+    std::fs::write(folder.join("a.json"), r#"{ "schema_version": 1, "root_mount_point": "Z" }"#).expect("Failed to write config");
+    let (res, err) = crate::manual_read_configs_from_folder(&folder);
+    assert!(err.is_err());
+    assert_eq!(res.map(|(config, _)| config.get_root_mount_point()), Some('Z'));
+}
+
+#[test]
+pub fn config_write_errors() {
+    let folder = TestDir::new("write");
+    let path = folder.join("tool.json");
+
+    assert_eq!(crate::manual_write_config(&path, GameBridgeConfig::default(), false), Ok(()));
+    assert_eq!(crate::manual_write_config(&path, GameBridgeConfig::default(), false), Err(ConfigError::AlreadyExists { path: path.clone() }));
+    assert_eq!(crate::manual_write_config(&path, GameBridgeConfig::default().with_override_root_mountpoint('L'), true), Ok(()));
+    assert_eq!(crate::manual_read_config(&path).map(|c| c.get_root_mount_point()), Ok('L'));
+    assert!(!folder.join("tool.json.tmp").exists());
+
+    // Another tool holding the lock only delays the write
+    let lock = FolderLock::acquire(folder.as_path()).expect("Failed to lock");
+    let writer = {
+        let path = path.clone();
+        std::thread::spawn(move || crate::manual_write_config(&path, GameBridgeConfig::default(), true))
+    };
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(!writer.is_finished());
+    drop(lock);
+    assert_eq!(writer.join().expect("Writer panicked"), Ok(()));
+
+    let missing = crate::manual_read_config(&folder.join("missing.json")).expect_err("File does not exist");
+    assert_eq!(missing.get_io_kind(), Some(std::io::ErrorKind::NotFound));
+
+    std::fs::write(folder.join("broken.json"), "{\n  \"maps\": [\n    { \"name\": 5 }\n  ]\n}").expect("Failed to write config");
+    match crate::manual_read_config(&folder.join("broken.json")) {
+        Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 3),
+        res => panic!("Expected a parse error, got {res:?}")
+    }
+
+    // The lock file is not a config, so only the broken one is reported
+    let (res, err) = crate::manual_read_configs_from_folder(&folder);
+    assert!(res.is_some());
+    assert_eq!(err.map_err(|e| e.0.len()), Err(1));
+}
+
+#[test]
+pub fn merge_report_provenance() {
+    let folder = TestDir::new("report");
+
+    let tool = || AppContainer::App(App::new("C:\\tool.exe".to_string()).expect("Valid path"));
+    let a = GameBridgeConfig::default()
+        .with_memory_maps(vec![MemMapConfig { name: "telemetry".to_string(), size: 512, event: None }])
+        .with_autolaunch_apps(vec![tool()]);
+    let b = GameBridgeConfig::default()
+        .with_memory_maps(vec![MemMapConfig { name: "telemetry".to_string(), size: 1024, event: None }, MemMapConfig { name: "scoring".to_string(), size: 64, event: None }])
+        .with_autolaunch_apps(vec![tool()])
+        .with_name_override("365960".to_string());
+    crate::manual_write_config(&folder.join("a.json"), a, false).expect("Failed to write");
+    crate::manual_write_config(&folder.join("b.json"), b, false).expect("Failed to write");
+
+    let (res, err, report) = crate::manual_read_configs_from_folder_with_report(&folder, None);
+    assert!(err.is_ok());
+    assert_eq!(res.map(|(config, _)| config.maps.iter().map(|m| m.size).collect::<Vec<_>>()), Some(vec![1024, 64]));
+
+    let mut files = report.files.clone();
+    files.sort();
+    assert_eq!(files, vec!["a.json", "b.json"]);
+
+    let telemetry = report.maps.iter().find(|e| e.name == "telemetry").expect("Map is reported");
+    assert_eq!(telemetry.sources.len(), 2);
+    assert_eq!(telemetry.notes, vec!["kept the largest size 1024".to_string()]);
+    assert_eq!(report.maps.iter().find(|e| e.name == "scoring").map(|e| e.sources.clone()), Some(vec!["b.json".to_string()]));
+    assert_eq!(report.conflicts.len(), 1);
+    assert!(report.conflicts[0].contains("512 (a.json)"));
+
+    assert_eq!(report.apps.len(), 1);
+    assert_eq!(report.apps[0].notes, vec!["deduplicated, runs once instead of 2 times".to_string()]);
+    assert_eq!(report.game_ids.len(), 2);
+
+    report.write(folder.as_path()).expect("Failed to write report");
+    let written = std::fs::read_to_string(folder.join(crate::report::REPORT_FILE)).expect("Report was written");
+    assert!(written.starts_with("Merged 2 config(s)"));
+
+    // The report is not read as a config
+    let (_, err, _) = crate::manual_read_configs_from_folder_with_report(&folder, None);
+    assert!(err.is_ok());
+}
+
+#[test]
+pub fn merge_priority_and_removals() {
+    let folder = TestDir::new("priority");
+
+    let map = |name: &str| MemMapConfig { name: name.to_string(), size: 64, event: None };
+    let tool = |path: &str| AppContainer::App(App::new(path.to_string()).expect("Valid path"));
+
+    let preset = GameBridgeConfig::default()
+        .with_memory_maps(vec![map("acpmf_physics"), map("acpmf_graphics"), map("other")])
+        .with_autolaunch_apps(vec![tool("C:\\Tools\\old.exe"), tool("C:\\Tools\\new.exe")])
+        .with_override_root_mountpoint('L');
+    let user = GameBridgeConfig::default()
+        .with_priority(10)
+        .with_override_root_mountpoint('M')
+        .with_removals(ConfigRemovals::default().with_map("acpmf_*".to_string()).with_app("c:\\tools\\OLD.exe".to_string()));
+    let disabled = GameBridgeConfig::default()
+        .with_priority(20)
+        .with_enabled(false)
+        .with_memory_maps(vec![map("disabled")]);
+
+    // Read back the same, defaults are not written
+    let text = serde_json::to_string(&user).expect("Failed to serialize");
+    assert!(!text.contains("enabled"));
+    let read: GameBridgeConfig = serde_json::from_str(text.as_str()).expect("Failed to parse");
+    assert_eq!(read.get_priority(), 10);
+    assert!(read.is_enabled());
+    assert_eq!(read.get_removals(), user.get_removals());
+
+    crate::manual_write_config(&folder.join("datalink-default.json"), preset, false).expect("Failed to write");
+    crate::manual_write_config(&folder.join("zz-user.json"), user, false).expect("Failed to write");
+    crate::manual_write_config(&folder.join("aa-disabled.json"), disabled, false).expect("Failed to write");
+
+    let (res, err, report) = crate::manual_read_configs_from_folder_with_report(&folder, None);
+    let (config, _) = res.expect("Configs were read");
+
+    // The user config is merged first, despite its name
+    assert_eq!(config.get_root_mount_point(), 'M');
+    assert_eq!(err.map_err(|e| e.0.len()), Err(1));
+    assert_eq!(report.files, vec!["zz-user.json", "datalink-default.json"]);
+    assert_eq!(report.disabled, vec!["aa-disabled.json"]);
+    assert_eq!(report.removed.len(), 3);
+
+    assert_eq!(config.maps.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), vec!["other"]);
+    assert_eq!(config.apps.iter().map(|a| a.to_string()).collect::<Vec<_>>(), vec!["C:\\Tools\\new.exe".to_string()]);
+}
+
+#[test]
+pub fn schema_migrates_and_warns() {
+    let path = std::path::Path::new("tool.json");
+
+    // Written before versioning
+    let (config, warnings) = schema::parse_config(path, br#"{ "maps": [ { "name": "telemetry", "size": 64 } ] }"#).expect("Failed to parse");
+    assert_eq!(config.get_schema_version(), schema::SCHEMA_VERSION);
+    assert_eq!(config.maps.len(), 1);
+    assert!(warnings.is_empty());
+
+    // Z meant unset before versioning, so the detected drive is used for those
+    let (mut config, _) = schema::parse_config(path, br#"{ "root_mount_point": "z", "apps": [ { "type": "Delete", "file": "/tmp/log" } ] }"#).expect("Failed to parse");
+    assert_eq!(config.apply_detected_root_mount_point('Y'), None);
+    assert_eq!(config.get_root_mount_point(), 'Y');
+    let (mut config, _) = schema::parse_config(path, br#"{ "root_mount_point": "L" }"#).expect("Failed to parse");
+    assert!(config.apply_detected_root_mount_point('Y').is_some());
+    let (mut config, _) = schema::parse_config(path, br#"{ "schema_version": 1, "root_mount_point": "Z" }"#).expect("Failed to parse");
+    assert!(config.apply_detected_root_mount_point('Y').is_some());
+    assert_eq!(config.get_root_mount_point(), 'Z');
+
+    // New configs are written with the version
+    let text = serde_json::to_string(&GameBridgeConfig::default()).expect("Failed to serialize");
+    assert!(text.contains(format!("\"schema_version\":{}", schema::SCHEMA_VERSION).as_str()));
+
+    let text = r#"{
+        "$schema": "datalink-config.schema.json",
+        "schema_version": 1,
+        "post_app": [],
+        "apps": [ { "path": "C:\\tool.exe", "args": [], "dealy_ms": 5 }, { "type": "Mkdir", "path": "C:\\logs" } ],
+        "process_tracking": { "executable": [] }
+    }"#;
+    let (config, warnings) = schema::parse_config(path, text.as_bytes()).expect("Failed to parse");
+    assert_eq!(config.apps.len(), 2);
+    let mut fields = warnings.iter().map(|w| match w {
+        ConfigError::UnknownField { field, .. } => field.clone(),
+        w => panic!("Unexpected warning {w:?}")
+    }).collect::<Vec<_>>();
+    fields.sort();
+    assert_eq!(fields, vec!["apps[0].dealy_ms", "post_app", "process_tracking.executable"]);
+    assert!(warnings.iter().all(|w| w.is_warning()));
+
+    // Newer configs are still read
+    let (_, warnings) = schema::parse_config(path, br#"{ "schema_version": 99 }"#).expect("Failed to parse");
+    assert_eq!(warnings, vec![ConfigError::NewerSchema { path: path.to_path_buf(), version: 99 }]);
+
+    assert!(matches!(schema::parse_config(path, br#"{ "schema_version": "one" }"#), Err(ConfigError::Schema { .. })));
+    assert!(matches!(schema::parse_config(path, b"[]"), Err(ConfigError::Schema { .. })));
+}
+
+#[cfg(feature = "schema")]
+#[test]
+pub fn schema_is_published() {
+    let published = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/datalink-config.schema.json")).expect("Schema is published");
+    let published: serde_json::Value = serde_json::from_str(published.as_str()).expect("Schema is valid json");
+    assert_eq!(published, schema::json_schema(), "Regenerate the schema with the generate_schema example");
+}
+
+#[test]
+pub fn tool_version_parse() {
+    assert_eq!("1.2.3".parse::<ToolVersion>(), Ok(ToolVersion::new(1, 2, 3)));
+    assert_eq!("v2".parse::<ToolVersion>(), Ok(ToolVersion::new(2, 0, 0)));
+    assert!("1.2.3.4".parse::<ToolVersion>().is_err());
+    assert!("1.x".parse::<ToolVersion>().is_err());
+    assert!(ToolVersion::new(1, 10, 0) > ToolVersion::new(1, 9, 5));
+    assert_eq!(serde_json::to_string(&ToolVersion::new(1, 2, 0)).ok(), Some("\"1.2.0\"".to_string()));
+}
+
+#[test]
+pub fn ensure_config_updates() {
+    // Not created yet, that is up to the code under test
+    let dir = TestDir::new("ensure");
+    let folder = dir.join("config");
+    let path = folder.join("tool.json");
+
+    let map = |name: &str, size: usize| MemMapConfig { name: name.to_string(), size, event: None };
+    let v1 = GameBridgeConfig::default().with_memory_maps(vec![map("telemetry", 64), map("legacy", 64)]);
+    let v2 = GameBridgeConfig::default().with_memory_maps(vec![map("telemetry", 128), map("legacy", 64)]);
+
+    assert_eq!(install::manual_ensure_config(&path, v1.clone(), ToolVersion::new(1, 0, 0)), Ok(EnsureOutcome::Created));
+    assert_eq!(install::manual_ensure_config(&path, v1.clone(), ToolVersion::new(1, 0, 0)), Ok(EnsureOutcome::UpToDate));
+
+    // The user adds a map and removes one of the tool
+    let edited = crate::manual_read_config(&path).expect("Failed to read")
+        .with_user_edits(UserEdits { maps: vec![map("mine", 32)], remove: ConfigRemovals::default().with_map("legacy".to_string()), ..Default::default() })
+        .with_enabled(false);
+    crate::manual_write_config(&path, edited, true).expect("Failed to write");
+
+    assert_eq!(install::manual_ensure_config(&path, v2.clone(), ToolVersion::new(1, 1, 0)), Ok(EnsureOutcome::Updated { from: Some(ToolVersion::new(1, 0, 0)) }));
+    assert_eq!(install::manual_ensure_config(&path, v1, ToolVersion::new(1, 0, 0)), Ok(EnsureOutcome::NewerInstalled { installed: ToolVersion::new(1, 1, 0) }));
+
+    let mut installed = crate::manual_read_config(&path).expect("Failed to read");
+    assert_eq!(installed.get_tool_version(), Some(ToolVersion::new(1, 1, 0)));
+    assert!(!installed.is_enabled());
+    assert_eq!(installed.maps.iter().map(|m| m.size).collect::<Vec<_>>(), vec![128, 64]);
+
+    assert_eq!(installed.apply_user_edits(), vec!["map legacy".to_string()]);
+    assert_eq!(installed.maps.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), vec!["telemetry", "mine"]);
+
+    // A config that can't be read is left alone
+    std::fs::write(path.as_path(), "{ broken").expect("Failed to write");
+    assert!(matches!(install::manual_ensure_config(&path, v2, ToolVersion::new(2, 0, 0)), Err(ConfigError::Parse { .. })));
+}
+
+#[test]
+pub fn jsonc_and_toml_configs() {
+    let jsonc = r#"{
+        // Written by hand
+        "game_id": "AssettoCorsaCompetizione", /* the override */
+        "root_mount_point": "L",
+        "notes": "url: https://example.com // not a comment",
+        "maps": [
+            { "name": "acpmf_static", "size": 2048 },
+            { "name": "acpmf_physics", "size": 2048, },
+        ],
+        "apps": [
+            { "path": "%APPDATA%\\Tool\\tool.exe", "args": ["--port", "9000"] },
+            { "type": "RegSet", "key": "HKCU\\Software\\Tool", "name": "Port", "value_type": "Dword", "data": 9000 },
+            { "type": "PatchIni", "file": "./game.ini", "set": { "UDP": { "Enabled": "1" } } },
+        ],
+        "overlays": [
+            { "target": "C:\\Games\\Plugins\\Telemetry.dll", "source": "/home/user/Telemetry.dll", "mode": "Symlink" }
+        ],
+    }"#;
+    let (config, warnings) = schema::parse_config(std::path::Path::new("tool.jsonc"), jsonc.as_bytes()).expect("Failed to parse jsonc");
+    assert!(warnings.is_empty());
+    assert_eq!(config.get_notes().map(|n| n.as_str()), Some("url: https://example.com // not a comment"));
+    assert_eq!(config.maps.len(), 2);
+    assert_eq!(config.apps.len(), 3);
+
+    // Everything survives the way through toml
+    let toml = ConfigFormat::Toml.serialize(&config).expect("Failed to write toml");
+    let (read, warnings) = schema::parse_config(std::path::Path::new("tool.toml"), toml.as_bytes()).expect("Failed to parse toml");
+    assert!(warnings.is_empty());
+    assert_eq!(serde_json::to_value(&read).ok(), serde_json::to_value(&config).ok());
+
+    let broken = "{\n  // comment\n  \"maps\": [\n    { \"name\": 5 },\n  ]\n}";
+    match schema::parse_config(std::path::Path::new("broken.json"), broken.as_bytes()) {
+        Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 4),
+        res => panic!("Expected a parse error, got {res:?}")
+    }
+    let broken = "schema_version = 1\n\n[[maps]]\nname = \"acpmf_static\"\nsize = \"large\"\n";
+    match schema::parse_config(std::path::Path::new("broken.toml"), broken.as_bytes()) {
+        Err(ConfigError::Parse { line, .. }) => assert_eq!(line, 5),
+        res => panic!("Expected a parse error, got {res:?}")
+    }
+
+    // The folder reader picks up both, and writers pick the format by the extension
+    let folder = TestDir::new("format");
+    std::fs::write(folder.join("hand.jsonc"), jsonc).expect("Failed to write config");
+    crate::manual_write_config(&folder.join("tool.toml"), GameBridgeConfig::default().with_memory_maps(vec![
+        MemMapConfig { name: "scoring".to_string(), size: 64, event: None }
+    ]), false).expect("Failed to write");
+    assert!(std::fs::read_to_string(folder.join("tool.toml")).expect("Failed to read").contains("[[maps]]"));
+
+    let (res, err) = crate::manual_read_configs_from_folder(&folder);
+    assert_eq!(err, Ok(()));
+    assert_eq!(res.map(|(c, _)| c.maps.len()), Some(3));
+}
+
+#[test]
+pub fn presets_for_all_ids() {
+    for id in presets::PRESET_GAME_IDS {
+        let preset = presets::get_preset(id).expect("Every id has a preset");
+        assert!(!preset.maps.is_empty());
+        assert!(preset.get_notes().is_some_and(|n| n.starts_with(format!("Datalink v{} default config for", presets::PRESET_VERSION).as_str())));
+        assert_eq!(preset.get_tool_version(), Some(presets::get_preset_version()));
+    }
+    assert_ne!(presets::get_preset_version(), ToolVersion::default());
+    assert_eq!(presets::get_preset_game_name("805550"), Some("Assetto Corsa Competizione"));
+    assert!(presets::get_preset("440").is_none());
 }
@@ -0,0 +1,215 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use datalink_bridge_config::{format::ConfigFormat, lock::FolderLock, presets, wildcard_match, Action, App, AppContainer, GameBridgeConfig, MemMapConfig};
+
+/// The kind of entry added to (or removed from) a config
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Entry {
+    Map,
+    App,
+    PostApp,
+    Action
+}
+
+impl Entry {
+    pub(crate) fn parse(kind: &str) -> Result<Self, String> {
+        match kind {
+            "map" => Ok(Self::Map),
+            "app" => Ok(Self::App),
+            "post-app" => Ok(Self::PostApp),
+            "action" => Ok(Self::Action),
+            _ => Err(format!("Unknown entry '{kind}', expected map, app, post-app or action"))
+        }
+    }
+}
+
+/// Path of the config with this name in the folder, `.json` is added if the name has no config extension
+pub(crate) fn get_config_path(folder: &Path, name: &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        return Err(format!("Invalid config name '{name}', expected a file name like com.example.tool.json"));
+    }
+
+    let path = folder.join(name);
+    if ConfigFormat::from_path(path.as_path()).is_some() {
+        Ok(path)
+    } else {
+        Ok(folder.join(format!("{name}.{}", ConfigFormat::Json.extension())))
+    }
+}
+
+/// Adds the entry to the config (created if it doesn't exist).
+///
+/// `values` are name and size for maps, path and args for apps, and the json of the action
+pub(crate) fn add(path: &Path, entry: Entry, values: Vec<String>) -> Result<String, String> {
+    let container = match entry {
+        Entry::Map => {
+            let (name, size) = match values.as_slice() {
+                [name, size] => (name.clone(), size.parse::<usize>().map_err(|_| format!("Invalid map size '{size}'"))?),
+                _ => return Err("Expected the name and size of the map".to_string())
+            };
+
+            let map = MemMapConfig { name, size, event: None };
+            return edit(path, true, |config, managed| {
+                let description = format!("map {} ({} bytes)", map.name, map.size);
+                if managed {
+                    let mut user = config.get_user_edits().clone();
+                    user.maps.push(map);
+                    config.set_user_edits(user);
+                } else {
+                    config.maps.push(map);
+                }
+                Ok(format!("Added {description}"))
+            });
+        },
+        Entry::App | Entry::PostApp => {
+            let mut values = values.into_iter();
+            let exec = values.next().ok_or("Expected the path of the app".to_string())?;
+            let app = App::new(exec.clone()).ok_or(format!("Invalid app path '{exec}'"))?;
+            AppContainer::App(app.with_args(values.collect()))
+        },
+        Entry::Action => {
+            let json = match values.as_slice() {
+                [json] => json,
+                _ => return Err("Expected the action as json (in quotes)".to_string())
+            };
+            let action: Action = serde_json::from_str(json.as_str()).map_err(|e| format!("Invalid action: {e}"))?;
+            AppContainer::Action(action)
+        }
+    };
+
+    edit(path, true, |config, managed| {
+        let description = describe(&container);
+        let mut user = config.get_user_edits().clone();
+        let list = match (managed, entry == Entry::PostApp) {
+            (true, true) => &mut user.post_apps,
+            (true, false) => &mut user.apps,
+            (false, true) => &mut config.post_apps,
+            (false, false) => &mut config.apps
+        };
+        list.push(container);
+
+        if managed {
+            config.set_user_edits(user);
+        }
+        Ok(format!("Added {description}"))
+    })
+}
+
+/// Removes the entries matching the pattern (`*` and `?` wildcards) from the config.
+///
+/// Maps match by name, apps by their path and actions by their description (as shown by `show`).
+/// Configs managed by a tool (and the preset) get updated by it, so their own entries are removed
+/// through their user section instead, which can't remove actions
+pub(crate) fn remove(path: &Path, entry: Entry, pattern: &str) -> Result<String, String> {
+    let matches = |item: &AppContainer| match (entry, item) {
+        (Entry::App | Entry::PostApp, AppContainer::App(app)) => wildcard_match(pattern, app.get_exec()),
+        (Entry::Action, AppContainer::Action(action)) => wildcard_match(pattern, action.get_description().as_str()),
+        _ => false
+    };
+
+    edit(path, false, |config, managed| {
+        let mut removed = Vec::<String>::new();
+        let mut user = config.get_user_edits().clone();
+
+        // Own entries, or the ones the user added to a managed config
+        let (maps, apps, post_apps) = if managed {
+            (&mut user.maps, &mut user.apps, &mut user.post_apps)
+        } else {
+            (&mut config.maps, &mut config.apps, &mut config.post_apps)
+        };
+        match entry {
+            Entry::Map => maps.retain(|map| {
+                let matched = wildcard_match(pattern, map.name.as_str());
+                if matched {
+                    removed.push(format!("map {}", map.name));
+                }
+                !matched
+            }),
+            Entry::App => apps.retain(|item| retain_unmatched(item, matches(item), &mut removed)),
+            Entry::PostApp => post_apps.retain(|item| retain_unmatched(item, matches(item), &mut removed)),
+            Entry::Action => {
+                apps.retain(|item| retain_unmatched(item, matches(item), &mut removed));
+                post_apps.retain(|item| retain_unmatched(item, matches(item), &mut removed));
+            }
+        }
+
+        if managed {
+            let tool_matched = match entry {
+                Entry::Map => config.maps.iter().any(|map| wildcard_match(pattern, map.name.as_str())),
+                Entry::App => config.apps.iter().any(matches),
+                Entry::PostApp => config.post_apps.iter().any(matches),
+                Entry::Action => config.apps.iter().chain(config.post_apps.iter()).any(matches)
+            };
+
+            if tool_matched {
+                let list = match entry {
+                    Entry::Map => &mut user.remove.maps,
+                    Entry::App => &mut user.remove.apps,
+                    Entry::PostApp => &mut user.remove.post_apps,
+                    Entry::Action => return Err("Actions of a config managed by a tool can't be removed, disable the config instead".to_string())
+                };
+                if !list.iter().any(|p| p == pattern) {
+                    list.push(pattern.to_string());
+                }
+                removed.push(format!("entries matching {pattern} of the tool (through the user section)"));
+            }
+            config.set_user_edits(user);
+        }
+
+        if removed.is_empty() {
+            return Err(format!("Nothing matches {pattern}"));
+        }
+        Ok(format!("Removed {}", removed.join(", ")))
+    })
+}
+
+/// Enables or disables the config, disabled configs are skipped by the bridge
+pub(crate) fn set_enabled(path: &Path, enabled: bool) -> Result<String, String> {
+    edit(path, false, |config, _| {
+        config.set_enabled(enabled);
+        Ok(format!("{} {}", if enabled { "Enabled" } else { "Disabled" }, path.display()))
+    })
+}
+
+/// Path and args of apps, the description of actions
+pub(crate) fn describe(item: &AppContainer) -> String {
+    match item {
+        AppContainer::App(app) if app.get_args_as_ref().is_empty() => app.get_exec().to_string(),
+        AppContainer::App(app) => format!("{} {}", app.get_exec(), app.get_args_as_ref().join(" ")),
+        AppContainer::Action(action) => action.get_description()
+    }
+}
+
+fn retain_unmatched(item: &AppContainer, matched: bool, removed: &mut Vec<String>) -> bool {
+    if matched {
+        removed.push(describe(item));
+    }
+    !matched
+}
+
+/// Reads, changes and writes the config while holding the lock on the folder.
+///
+/// The closure is told if the config is managed (installed by a tool, or the preset), as those
+/// are replaced on updates except for their user section
+fn edit<F>(path: &Path, create: bool, change: F) -> Result<String, String>
+    where F: FnOnce(&mut GameBridgeConfig, bool) -> Result<String, String> {
+    let folder = path.parent().ok_or(format!("Invalid config path {}", path.display()))?;
+    if create && !folder.exists() {
+        fs::create_dir_all(folder).map_err(|e| format!("Failed to create {}: {e}", folder.display()))?;
+    }
+    let lock = FolderLock::acquire(folder)?;
+
+    let mut config = if path.exists() {
+        datalink_bridge_config::manual_read_config(path)?
+    } else if create {
+        GameBridgeConfig::default()
+    } else {
+        return Err(format!("{} does not exist", path.display()));
+    };
+
+    let managed = config.get_tool_version().is_some() || path.file_name().is_some_and(|name| name == presets::PRESET_FILE);
+    let message = change(&mut config, managed)?;
+    lock.write_config(path, &config, true)?;
+
+    Ok(message)
+}
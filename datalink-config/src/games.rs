@@ -0,0 +1,84 @@
+use std::{fs, path::PathBuf};
+
+use datalink_bridge_config::{presets, CONFIG_FOLDER_NAME};
+use proton_finder::linux::{self, SteamLibrary, VdfValue};
+
+/// A Steam game with a Datalink config folder in its prefix
+pub(crate) struct Game {
+    pub id: u32,
+    pub name: Option<String>,
+    pub folder: PathBuf
+}
+
+/// Finds the config folder within the prefix of this game.
+///
+/// The folder might not exist yet, but the prefix does (so the game was launched at least once)
+pub(crate) fn get_config_folder(game_id: u32) -> Result<PathBuf, String> {
+    let drive = match proton_finder::get_game_drive(game_id) {
+        Ok(drive) => drive,
+        Err(drive) => {
+            println!("Warning: $STEAM_DIR is set, but does not point to a Steam installation");
+            drive
+        }
+    };
+
+    let drive = drive.ok_or(format!("No proton prefix found for {game_id}, the game has to be installed and launched once"))?;
+    let mut folder = drive.config_dir().ok_or(format!("Failed to find the AppData folder in the prefix of {game_id}"))?;
+    folder.push(CONFIG_FOLDER_NAME);
+    Ok(folder)
+}
+
+/// Lists all games (of all Steam installations) that have a config folder in their prefix, by AppId
+pub(crate) fn find_games() -> Vec<Game> {
+    let roots = linux::find_all_steam_roots().unwrap_or_else(|roots| {
+        println!("Warning: $STEAM_DIR is set, but does not point to a Steam installation");
+        roots
+    });
+
+    let mut games = Vec::<Game>::new();
+    for root in roots {
+        let libraries = root.get_libraries();
+
+        for lib in libraries.iter() {
+            let mut compatdata = lib.get_steamapps_folder();
+            compatdata.push("compatdata");
+            let dir = match fs::read_dir(compatdata.as_path()) {
+                Ok(dir) => dir,
+                Err(_) => continue
+            };
+
+            for id in dir.filter_map(|item| item.ok()?.file_name().to_str()?.parse::<u32>().ok()) {
+                if games.iter().any(|game| game.id == id) {
+                    // Same game in multiple steam roots (or leftovers in another library)
+                    continue;
+                }
+
+                if let Some(mut folder) = lib.get_prefix(id).and_then(|prefix| prefix.appdata_roaming()) {
+                    folder.push(CONFIG_FOLDER_NAME);
+                    if folder.is_dir() {
+                        games.push(Game { id, name: get_game_name(libraries.as_slice(), id), folder });
+                    }
+                }
+            }
+        }
+    }
+
+    games.sort_by_key(|game| game.id);
+    games
+}
+
+/// The name from the app manifest (in whichever library the game is installed), else the name of the preset
+fn get_game_name(libraries: &[SteamLibrary], game_id: u32) -> Option<String> {
+    for lib in libraries {
+        let mut manifest = lib.get_steamapps_folder();
+        manifest.push(format!("appmanifest_{game_id}.acf"));
+
+        if let Some(VdfValue::Complex(state)) = linux::parse_vdf_file(&manifest).and_then(|vdf| vdf.pairs.get("AppState").cloned()) {
+            if let Some(VdfValue::Simple(name)) = state.pairs.get("name") {
+                return Some(name.clone());
+            }
+        }
+    }
+
+    presets::get_preset_game_name(game_id.to_string().as_str()).map(|name| name.to_string())
+}
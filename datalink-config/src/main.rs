@@ -0,0 +1,311 @@
+use std::{fs, path::Path};
+
+use datalink_bridge_config::{install::{self, ToolVersion}, policy::{self, Policy}, presets, GameBridgeConfig};
+
+mod edit;
+
+mod games;
+
+mod built_info {
+    include!(concat!(env!("OUT_DIR"), "/built.rs"));
+}
+
+fn main() {
+    let mut args = std::env::args();
+
+    let res = match args.nth(1).as_deref() {
+        Some("list") => list(),
+        Some("show") => show(args),
+        Some("add") => add_or_remove(args, true),
+        Some("remove") => add_or_remove(args, false),
+        Some("enable") => enable(args, true),
+        Some("disable") => enable(args, false),
+        Some("init") => init(args),
+        Some("--help") | Some("-h") | Some("help") | None => {
+            print_help();
+            Ok(())
+        },
+        Some(command) => Err(format!("Unknown command '{command}', see datalink-config --help"))
+    };
+
+    if let Err(e) = res {
+        println!("Error: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// Lists every game with a config folder, and the configs in it
+fn list() -> Result<(), String> {
+    let games = games::find_games();
+    if games.is_empty() {
+        println!("No game with Datalink configs found (the folder is created when the game is launched with Datalink)");
+    }
+
+    for game in games {
+        println!("{} {}", game.id, game.name.as_deref().unwrap_or("(unknown name)"));
+        println!("    {}", game.folder.display());
+
+        let mut files = fs::read_dir(game.folder.as_path())
+            .map(|dir| dir.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| datalink_bridge_config::format::ConfigFormat::from_path(p).is_some()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        files.sort();
+
+        for path in files {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            match datalink_bridge_config::manual_read_config(path.as_path()) {
+                Ok(config) => println!("    - {name}{}", describe_config(&config)),
+                Err(e) => println!("    - {name} (failed to read: {e})")
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows the merged config of a game (as the bridge would use it), or a single config file
+fn show(mut args: std::env::Args) -> Result<(), String> {
+    let game_id = parse_game_id(args.next())?;
+    let folder = games::get_config_folder(game_id)?;
+    if !folder.is_dir() {
+        return Err(format!("{} does not exist, the game was not launched with Datalink yet", folder.display()));
+    }
+
+    if let Some(name) = args.next() {
+        let path = edit::get_config_path(folder.as_path(), name.as_str())?;
+        let (config, warnings) = datalink_bridge_config::manual_read_config_with_warnings(path.as_path())?;
+        println!("{}{}", path.display(), describe_config(&config));
+        print_config(&config);
+        for warning in warnings {
+            println!("Warning: {warning}");
+        }
+        return Ok(());
+    }
+
+    let policy = read_policy();
+    let (config, err, report) = datalink_bridge_config::manual_read_configs_from_folder_with_report(folder.as_path(), policy.as_ref());
+    match config {
+        Some((config, alt)) => {
+            println!("Merged config of {game_id} ({}):", folder.display());
+            print_config(&config);
+            if let Some(alt) = alt {
+                println!("Also notified as: {}", alt.join(", "));
+            }
+        },
+        None => println!("No configs in {}", folder.display())
+    }
+    if let Err(err) = err {
+        println!("{err}");
+    }
+    if policy.is_some() {
+        println!("Checked against the policy (paths are checked by the bridge, once the placeholders are resolved)");
+    }
+    println!("\n{report}");
+
+    Ok(())
+}
+
+/// `add|remove <appid> <config> <map|app|post-app|action> <values...>`
+fn add_or_remove(mut args: std::env::Args, add: bool) -> Result<(), String> {
+    let game_id = parse_game_id(args.next())?;
+    let name = args.next().ok_or("Missing the name of the config".to_string())?;
+    let entry = edit::Entry::parse(args.next().ok_or("Missing what to change (map, app, post-app or action)".to_string())?.as_str())?;
+
+    let folder = games::get_config_folder(game_id)?;
+    let path = edit::get_config_path(folder.as_path(), name.as_str())?;
+    let before = fs::read(path.as_path()).ok();
+    let message = if add {
+        edit::add(path.as_path(), entry, args.collect())?
+    } else {
+        let pattern = args.next().ok_or("Missing the name, path or description to remove".to_string())?;
+        edit::remove(path.as_path(), entry, pattern.as_str())?
+    };
+
+    println!("{message}");
+    warn_on_lost_trust(path.as_path(), before);
+    Ok(())
+}
+
+/// `enable|disable <appid> <config>`
+fn enable(mut args: std::env::Args, enabled: bool) -> Result<(), String> {
+    let game_id = parse_game_id(args.next())?;
+    let name = args.next().ok_or("Missing the name of the config".to_string())?;
+
+    let folder = games::get_config_folder(game_id)?;
+    let path = edit::get_config_path(folder.as_path(), name.as_str())?;
+    let before = fs::read(path.as_path()).ok();
+    println!("{}", edit::set_enabled(path.as_path(), enabled)?);
+    warn_on_lost_trust(path.as_path(), before);
+    Ok(())
+}
+
+/// Warns if the policy allowed the config before the edit (through its sha256), but no longer does.
+///
+/// Any edit changes the hash, and we can't update the policy for the user, as the hash is what
+/// they checked the config against
+fn warn_on_lost_trust(path: &Path, before: Option<Vec<u8>>) {
+    let (policy, before) = match (read_policy(), before) {
+        (Some(policy), Some(before)) => (policy, before),
+        _ => return
+    };
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+    match fs::read(path) {
+        Ok(after) if policy.allows_config(name, before.as_slice()) && !policy.allows_config(name, after.as_slice()) => {
+            println!(
+                "Warning: {name} was allowed by its sha256 in the policy, which changed with this edit.\n\
+                The bridge skips its apps, actions and overlays until the new sha256 {} is in allowed_hashes",
+                policy::hash_hex(after.as_slice())
+            );
+        },
+        _ => ()
+    }
+}
+
+/// Writes the presets into the prefixes of the games (all installed ones with a preset, if no
+/// AppId is given), the same way the bridge does on launch
+fn init(args: std::env::Args) -> Result<(), String> {
+    let mut force = false;
+    let mut ids = Vec::<String>::new();
+    for arg in args {
+        match arg.as_str() {
+            "--force" | "-f" => force = true,
+            _ => ids.push(arg)
+        }
+    }
+
+    let explicit = !ids.is_empty();
+    if !explicit {
+        ids = presets::PRESET_GAME_IDS.iter().map(|id| id.to_string()).collect();
+    }
+
+    // The same version the bridge installs them with, so neither replaces the presets of the other
+    let version = presets::get_preset_version();
+    for id in ids {
+        let game_id = parse_game_id(Some(id.clone()))?;
+        let (name, preset) = match (presets::get_preset_game_name(id.as_str()), presets::get_preset(id.as_str())) {
+            (Some(name), Some(preset)) => (name, preset),
+            _ => {
+                println!("{id}: there is no preset for this game");
+                continue;
+            }
+        };
+
+        let folder = match games::get_config_folder(game_id) {
+            Ok(folder) => folder,
+            Err(e) => {
+                // Not installed games are only worth mentioning if they were asked for
+                if explicit {
+                    println!("{name} ({id}): {e}");
+                }
+                continue;
+            }
+        };
+
+        match write_preset(folder.as_path(), preset, version, force) {
+            Ok(message) => println!("{name} ({id}): {message}"),
+            Err(e) => println!("{name} ({id}): Failed to install the preset: {e}")
+        }
+    }
+
+    Ok(())
+}
+
+/// Installs (or updates) the preset the same way the bridge does, see `install::manual_ensure_config`.
+///
+/// If the folder exists but the preset does not, the user deleted it to disable it, so it is only
+/// written again with force
+fn write_preset(folder: &Path, preset: GameBridgeConfig, version: ToolVersion, force: bool) -> Result<String, String> {
+    let path = folder.join(presets::PRESET_FILE);
+    if folder.exists() && !path.exists() && !force {
+        return Ok(format!("Skipped, {} was deleted (which disables it), use --force to write it again", presets::PRESET_FILE));
+    }
+
+    let outcome = install::manual_ensure_config(path.as_path(), preset, version)?;
+    Ok(format!("{outcome} {}", path.display()))
+}
+
+/// The policy in ~/.config/Datalink/policy.json, which Datalink passes to the bridge
+fn read_policy() -> Option<Policy> {
+    let mut path = dirs::config_dir()?;
+    path.push("Datalink");
+    path.push("policy.json");
+    if !path.is_file() {
+        return None;
+    }
+
+    let res = fs::read_to_string(path.as_path())
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(text.as_str()).map_err(|e| e.to_string()));
+
+    match res {
+        Ok(policy) => Some(policy),
+        Err(e) => {
+            println!("Failed to read policy {}: {e}\nThe bridge will not run any apps, actions and overlays", path.display());
+            Some(Policy::default())
+        }
+    }
+}
+
+fn parse_game_id(arg: Option<String>) -> Result<u32, String> {
+    let arg = arg.ok_or("Missing the Steam AppId of the game".to_string())?;
+    arg.parse().map_err(|_| format!("Invalid Steam AppId '{arg}'"))
+}
+
+/// State of the config, as shown behind its name
+fn describe_config(config: &GameBridgeConfig) -> String {
+    let mut state = String::new();
+    if !config.is_enabled() {
+        state.push_str(" [disabled]");
+    }
+    if config.get_priority() != 0 {
+        state.push_str(format!(" [priority {}]", config.get_priority()).as_str());
+    }
+    if let Some(version) = config.get_tool_version() {
+        state.push_str(format!(" [version {version}]").as_str());
+    }
+    state
+}
+
+fn print_config(config: &GameBridgeConfig) {
+    match serde_json::to_string_pretty(config) {
+        Ok(text) => println!("{text}"),
+        Err(e) => println!("Failed to print config: {e}")
+    }
+}
+
+fn print_help() {
+    println!("
+datalink-config version {}
+Repository: {}
+
+Lists, shows and edits the Datalink configs within the proton prefixes of Steam games
+(in drive_c/users/steamuser/AppData/Roaming/Datalink/ of the prefix).
+Games are given by their Steam AppId, configs by their file name (.json is added if there is no extension).
+
+Usage:
+datalink-config list
+    Lists all games with a Datalink config folder, and the configs within
+datalink-config show <appid> [config]
+    Shows the merged config (as the bridge uses it, checked against ~/.config/Datalink/policy.json),
+    and where each entry came from. With a config name only that config is shown
+datalink-config add <appid> <config> map <name> <size>
+datalink-config add <appid> <config> app|post-app <path> [args...]
+datalink-config add <appid> <config> action '<json>'
+    Adds the entry to the config (which is created if missing), for example:
+    datalink-config add 805550 user.json action '{{\"type\": \"Mkdir\", \"path\": \"C:\\\\logs\"}}'
+datalink-config remove <appid> <config> map|app|post-app|action <pattern>
+    Removes maps by name, apps by path and actions by their description (as shown by show),
+    * and ? wildcards are supported
+datalink-config enable <appid> <config>
+datalink-config disable <appid> <config>
+    Disabled configs are skipped by the bridge
+datalink-config init [--force] [appid...]
+    Writes the presets Datalink ships into the prefixes of installed games (all games with a preset
+    if no AppId is given). Presets written by a newer version of Datalink are kept, as are presets
+    that fail to parse. A deleted preset is only written again with --force
+
+Configs installed by a tool (and the preset) are replaced when the tool updates them, so add and
+remove change their user section instead, which is kept.
+Comments in edited configs are not kept.
+", built_info::PKG_VERSION, built_info::PKG_REPOSITORY);
+}
@@ -0,0 +1,497 @@
+//! Launching (and closing) of the auxiliary apps, as well as performing actions.
+//!
+//! Apps are launched in order. Once an app has start conditions (delay, waiting for a process,
+//! file or map data), it and all following apps are handed off to a background thread, so the
+//! game launch is not delayed by them. Apps set to wait are run to completion before moving on.
+//!
+//! While the game runs the apps are watched, exits are logged, failures reported to Datalink and
+//! the app restarted according to its restart policy.
+
+use std::{path::PathBuf, process::Child, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, thread::JoinHandle, time::{Duration, Instant}};
+
+use datalink_bridge_config::{Action, App, AppContainer, WindowMode};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use windows::{
+    core::BOOL,
+    Win32::{
+        Foundation::{HWND, LPARAM},
+        UI::WindowsAndMessaging::{EnumWindows, GetWindowThreadProcessId, IsWindowVisible, ShowWindow, SW_SHOWMINNOACTIVE},
+    },
+};
+
+use crate::mmap::{self, FileMapping};
+
+/// How often start conditions are checked
+const CONDITION_POLL_RATE: Duration = Duration::from_millis(250);
+
+/// How often the running apps are checked for having exited
+const WATCH_POLL_RATE: Duration = Duration::from_millis(500);
+
+/// How long we look for the windows of an app that should start minimized
+const MINIMIZE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A launched app, with what is needed to restart it
+struct RunningApp {
+    app: App,
+    child: Child,
+    restarts: u32,
+    restart_at: Option<Instant>,
+    exited: bool
+}
+
+impl RunningApp {
+    fn new(app: App, child: Child) -> Self {
+        RunningApp { app, child, restarts: 0, restart_at: None, exited: false }
+    }
+
+    /// Checks if the app exited, reports failures and (re)starts it according to the restart policy
+    fn check(&mut self, root: char, callback: &str, game_ids: &[String]) {
+        let name = self.app.get_name().to_string();
+
+        if let Some(at) = self.restart_at {
+            if Instant::now() < at {
+                return;
+            }
+            self.restart_at = None;
+
+            match start_side_app(root, self.app.clone()) {
+                Ok(child) => {
+                    self.child = child;
+                    self.exited = false;
+                },
+                Err(e) => println!("Unable to restart App {name}: {e}")
+            }
+            return;
+        }
+
+        if self.exited {
+            return;
+        }
+
+        let status = match self.child.try_wait() {
+            Ok(None) => return,
+            Ok(Some(status)) => Some(status),
+            Err(_) => None
+        };
+        self.exited = true;
+
+        match status {
+            Some(status) if status.success() => {
+                println!("App {name} exited");
+                return;
+            },
+            Some(status) => println!("App {name} exited with {status}"),
+            None => println!("App {name} exited, but its exit status is unknown")
+        }
+
+        let code = status.and_then(|s| s.code()).unwrap_or(-1);
+        for game in game_ids {
+            crate::send_app_crashed(callback, game.as_str(), name.as_str(), code);
+        }
+
+        if let Some(backoff) = self.app.get_restart().get_backoff(self.restarts) {
+            self.restarts += 1;
+            self.restart_at = Some(Instant::now() + backoff);
+            println!("Restarting App {name} in {}ms (restart {})", backoff.as_millis(), self.restarts);
+        }
+    }
+}
+
+/// The apps launched for this session
+pub(crate) struct RunningApps {
+    apps: Arc<Mutex<Vec<RunningApp>>>,
+    running: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>
+}
+
+impl Default for RunningApps {
+    fn default() -> Self {
+        RunningApps { apps: Arc::new(Mutex::new(Vec::new())), running: Arc::new(AtomicBool::new(true)), threads: Vec::new() }
+    }
+}
+
+impl RunningApps {
+    pub fn is_empty(&self) -> bool {
+        self.threads.is_empty() && self.apps.lock().map(|a| a.is_empty()).unwrap_or(true)
+    }
+
+    /// Starts watching the apps (including the ones still to be launched) for exits,
+    /// reporting failures to all game_ids through the callback
+    pub fn watch(&mut self, root: char, callback: String, game_ids: Vec<String>) {
+        let apps = self.apps.clone();
+        let running = self.running.clone();
+
+        self.threads.push(std::thread::spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                if let Ok(mut apps) = apps.lock() {
+                    for item in apps.iter_mut() {
+                        item.check(root, callback.as_str(), &game_ids);
+                    }
+                }
+
+                std::thread::sleep(WATCH_POLL_RATE);
+            }
+        }));
+    }
+
+    /// Stops launching, watching and restarting apps, and closes all running ones according to
+    /// their shutdown policy.
+    ///
+    /// Returns false if the cleanup was not complete (see `close_apps`)
+    pub fn close(self, root: char) -> bool {
+        self.running.store(false, Ordering::Relaxed);
+
+        for thread in self.threads {
+            let _ = thread.join();
+        }
+
+        let apps = match self.apps.lock() {
+            Ok(mut apps) => std::mem::take(&mut *apps),
+            Err(e) => std::mem::take(&mut *e.into_inner())
+        };
+
+        close_apps(root, apps.into_iter().map(|a| (a.app, a.child)).collect())
+    }
+}
+
+/// Launches the apps and performs the actions in order.
+///
+/// Errors before the first app with start conditions is reached are returned (with all already
+/// launched apps closed), later ones can only be logged, as the game is already running by then
+pub(crate) fn launch_apps(root: char, apps: Vec<AppContainer>, maps: &[FileMapping]) -> Result<RunningApps, String> {
+    let mut running = RunningApps::default();
+    let mut iter = apps.into_iter();
+
+    while let Some(item) = iter.next() {
+        if let AppContainer::App(app) = &item {
+            if app.has_start_conditions() {
+                // This and all following apps are launched in the background
+                let mut rest = vec![item];
+                rest.extend(iter);
+
+                let maps = maps.iter().map(|m| (m.get_name().to_string(), m.get_path().to_path_buf())).collect();
+                let list = running.apps.clone();
+                let flag = running.running.clone();
+
+                running.threads.push(std::thread::spawn(move || launch_in_background(root, rest, maps, list, flag)));
+                break;
+            }
+        }
+
+        if let Err(e) = launch_item(root, item, &running.apps, &running.running) {
+            running.close(root);
+            return Err(e);
+        }
+    }
+
+    Ok(running)
+}
+
+fn launch_in_background(root: char, apps: Vec<AppContainer>, maps: Vec<(String, PathBuf)>, list: Arc<Mutex<Vec<RunningApp>>>, running: Arc<AtomicBool>) {
+    for item in apps {
+        if let AppContainer::App(app) = &item {
+            if !wait_for_conditions(root, app, &maps, &running) {
+                if !running.load(Ordering::Relaxed) {
+                    // Game closed before we got to launch the rest
+                    break;
+                }
+                continue;
+            }
+        }
+
+        if let Err(e) = launch_item(root, item, &list, &running) {
+            println!("Unable to run app/action: {e}");
+        }
+    }
+}
+
+/// Performs the app/action, adding launched apps to the list
+fn launch_item(root: char, item: AppContainer, list: &Mutex<Vec<RunningApp>>, running: &AtomicBool) -> Result<(), String> {
+    if let Some((app, child)) = perform_side_app(root, item, running)? {
+        if let Ok(mut list) = list.lock() {
+            list.push(RunningApp::new(app, child));
+        }
+    }
+
+    Ok(())
+}
+
+/// Waits until the start conditions of the app are met.
+///
+/// Returns false if the app should not be launched (timeout, or the game closed)
+fn wait_for_conditions(root: char, app: &App, maps: &[(String, PathBuf)], running: &AtomicBool) -> bool {
+    let name = app.get_name();
+    let start = Instant::now();
+    let timed_out = || app.get_timeout().is_some_and(|t| start.elapsed() >= t);
+
+    if let Some(delay) = app.get_delay() {
+        while start.elapsed() < delay {
+            if !running.load(Ordering::Relaxed) {
+                return false;
+            }
+            std::thread::sleep(delay.saturating_sub(start.elapsed()).min(CONDITION_POLL_RATE));
+        }
+    }
+
+    let file = app.get_wait_for_file_path(root);
+    if app.get_wait_for_file().is_some() && file.is_none() {
+        println!("Unable to convert path of the file App {name} waits for, skipping it");
+        return false;
+    }
+
+    let map = if let Some(map) = app.get_wait_for_map_data() {
+        if let Some((_, path)) = maps.iter().find(|(n, _)| n == map) {
+            Some(path)
+        } else {
+            println!("App {name} waits for map {map}, which is not a configured memory map, skipping it");
+            return false;
+        }
+    } else {
+        None
+    };
+
+    let mut system = System::new();
+    loop {
+        if !running.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        let file_met = file.as_ref().is_none_or(|f| f.exists());
+        let map_met = map.is_none_or(|m| mmap::file_has_data(m));
+        let process_met = app.get_wait_for_process().is_none_or(|pattern| {
+            system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+            system.processes().values().any(|pro| pro.name().to_str().is_some_and(|n| datalink_bridge_config::wildcard_match(pattern, n)))
+        });
+
+        if file_met && map_met && process_met {
+            return true;
+        }
+
+        if timed_out() {
+            println!("App {name} timed out waiting for its start conditions, skipping it");
+            return false;
+        }
+
+        std::thread::sleep(CONDITION_POLL_RATE);
+    }
+}
+
+/// Launches the app (returning it with its process) or performs the action.
+///
+/// Apps set to wait are run to completion, unless running is cleared (the game exited) first
+pub(crate) fn perform_side_app(root: char, app: AppContainer, running: &AtomicBool) -> Result<Option<(App, Child)>, String> {
+    match app {
+        AppContainer::App(app) => {
+            if !app.should_run(root) {
+                println!("Condition for App {} not met, skipping it", app.get_name());
+                return Ok(None);
+            }
+
+            let child = start_side_app(root, app.clone())?;
+
+            if app.waits() {
+                wait_for_completion(root, &app, child, running)?;
+                return Ok(None);
+            }
+
+            Ok(Some((app, child)))
+        },
+        AppContainer::Action(action @ (Action::RegSet { .. } | Action::RegDelete { .. })) => {
+            crate::registry::perform(action).map(|_| None)
+        },
+        AppContainer::Action(action) => {
+            match action.perform(root) {
+                Ok(()) => Ok(None),
+                Err(e) => Err(e.to_string())
+            }
+        }
+    }
+}
+
+/// Waits for the app to exit, errors if it fails or times out (in which case it is killed).
+///
+/// If running is cleared first, the app is closed according to its shutdown policy
+fn wait_for_completion(root: char, app: &App, mut child: Child, running: &AtomicBool) -> Result<(), String> {
+    let name = app.get_name();
+    let start = Instant::now();
+    println!("Waiting for App {name} to finish...");
+
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => {
+                println!("App {name} finished");
+                return Ok(());
+            },
+            Ok(Some(status)) => return Err(format!("App {name} failed with {status}")),
+            Ok(None) => (),
+            Err(e) => return Err(format!("Unable to wait for App {name}: {e}"))
+        }
+
+        if start.elapsed() >= app.get_wait_timeout() {
+            taskkill(child.id(), app.get_shutdown().kills_tree(), true);
+            return Err(format!("App {name} did not finish in time, it was terminated"));
+        }
+
+        if !running.load(Ordering::Relaxed) {
+            close_apps(root, vec![(app.clone(), child)]);
+            return Err(format!("App {name} did not finish before the game exited, it was closed"));
+        }
+
+        std::thread::sleep(CONDITION_POLL_RATE);
+    }
+}
+
+/// Starts another app on the side
+fn start_side_app(root: char, app: datalink_bridge_config::App) -> Result<std::process::Child, String> {
+    let name = app.get_name().to_string();
+
+    let window = app.get_window();
+
+    let mut cmd: std::process::Command = app.get_command(root).ok_or(format!("Failed to generate command for App {name}"))?;
+
+    let child = cmd.spawn().map_err(|e|  format!("Failed to spawn process for App {name}: {}", e.to_string()))?;
+    println!("Successfully launched App {name}");
+
+    if window == WindowMode::Minimized {
+        minimize_windows(child.id());
+    }
+    Ok(child)
+}
+
+/// Minimizes the windows of the process once they appear (in the background).
+///
+/// Creation flags can not do this for gui apps, and std does not expose the startup info
+fn minimize_windows(pid: u32) {
+    std::thread::spawn(move || {
+        let start = Instant::now();
+
+        while start.elapsed() < MINIMIZE_TIMEOUT {
+            let mut search = (pid, false);
+            let _ = unsafe { EnumWindows(Some(minimize_window), LPARAM(&mut search as *mut (u32, bool) as isize)) };
+
+            if search.1 {
+                return;
+            }
+            std::thread::sleep(CONDITION_POLL_RATE);
+        }
+    });
+}
+
+/// EnumWindows callback, lparam points to the (pid, found) we are searching for
+unsafe extern "system" fn minimize_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let search = unsafe { &mut *(lparam.0 as *mut (u32, bool)) };
+
+    let mut pid = 0_u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+
+    if pid == search.0 && unsafe { IsWindowVisible(hwnd) }.as_bool() {
+        let _ = unsafe { ShowWindow(hwnd, SW_SHOWMINNOACTIVE) };
+        search.1 = true;
+    }
+
+    // Continue, the app might have more than one window
+    BOOL::from(true)
+}
+
+const CLOSING_POLLING_RATE: Duration = Duration::from_millis(250);
+
+/// How long we wait for force killed apps to be gone
+const KILL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Closes the apps according to their shutdown policy. Apps that do not close within their
+/// timeout are killed.
+///
+/// If we can't get the state of an app we carry on with the others and return false to signal
+/// not complete (but likely sufficient cleanup)
+pub(crate) fn close_apps(root: char, apps: Vec<(App, Child)>) -> bool {
+    let mut clean = true;
+    let mut pending = Vec::<(App, Child)>::with_capacity(apps.len());
+
+    for (app, mut child) in apps {
+        match child.try_wait() {
+            Ok(Some(_)) => (),
+            Ok(None) => {
+                let shutdown = app.get_shutdown();
+
+                if shutdown.is_detached() {
+                    println!("Leaving App {} running", app.get_name());
+                    continue;
+                }
+
+                if let Some(stop) = shutdown.get_stop_command() {
+                    match stop.clone().get_command(root).map(|mut c| c.spawn()) {
+                        Some(Ok(_)) => (),
+                        _ => {
+                            println!("Failed to run the stop command of App {}, asking it to close instead", app.get_name());
+                            taskkill(child.id(), shutdown.kills_tree(), false);
+                        }
+                    }
+                } else {
+                    taskkill(child.id(), shutdown.kills_tree(), false);
+                }
+
+                pending.push((app, child));
+            },
+            Err(e) => {
+                let _ = e;
+                clean = false;
+            }
+        }
+    }
+
+
+    // Waiting for gracefull termination
+    let start = Instant::now();
+    let mut killed = Vec::<Child>::new();
+
+    while !pending.is_empty() {
+        let poll_start = Instant::now();
+
+        let mut index = 0;
+        while let Some((app, child)) = pending.get_mut(index) {
+            match child.try_wait() {
+                Ok(None) => {
+                    if start.elapsed() >= app.get_shutdown().get_timeout() {
+                        // Forcefull termination
+                        println!("App {} reached timeout for graceful shutdown, forcefull shutdown used", app.get_name());
+                        taskkill(child.id(), app.get_shutdown().kills_tree(), true);
+
+                        killed.push(pending.swap_remove(index).1);
+                    } else {
+                        index += 1;
+                    }
+                },
+                _ => {
+                    pending.swap_remove(index);
+                }
+            }
+        }
+
+        if let Some(time) = CLOSING_POLLING_RATE.checked_sub(poll_start.elapsed()) {
+            std::thread::sleep(time);
+        }
+    }
+
+    // Giving the killed apps some time to actually be gone
+    let start = Instant::now();
+    while start.elapsed() < KILL_TIMEOUT && killed.iter_mut().any(|c| matches!(c.try_wait(), Ok(None))) {
+        std::thread::sleep(CLOSING_POLLING_RATE);
+    }
+
+    clean
+}
+
+/// Asks the process to close (or kills it if force), including its children if tree
+fn taskkill(pid: u32, tree: bool, force: bool) {
+    let mut killer = std::process::Command::new("taskkill");
+    if force {
+        killer.arg("/f");
+    }
+    if tree {
+        killer.arg("/t");
+    }
+    killer.arg("/pid");
+    killer.arg(pid.to_string());
+
+    let _ = killer.spawn();
+}
@@ -0,0 +1,158 @@
+//! Some games (or their plugins) signal fresh telemetry through a named event next to their
+//! memory map. Linux software can not wait on these, so the bridge waits on them instead and
+//! counts every signal into a small `[map].seq` file next to the map in `/dev/shm`.
+//!
+//! The file contains a single little endian u32, which is written with a regular file write (so
+//! linux readers can block on it via inotify `IN_MODIFY`, instead of busy polling the map).
+//!
+//! Only events the bridge creates itself (before the game is launched) are bridged. These are auto
+//! reset, so every wait consumes exactly one signal and the bridge never has to reset them. An
+//! event that already exists belongs to someone else, with a reset behavior we don't know: waiting
+//! on a manual reset one would count it over and over, resetting it would steal signals from its
+//! readers, so these are skipped.
+//!
+//! Only events are supported, not mutexes: a mutex guarding the map is free whenever nobody is
+//! writing, so waiting on it can't tell new data apart from no data.
+
+use std::{fs::{self, File}, os::windows::fs::{FileExt, OpenOptionsExt}, path::PathBuf, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread::JoinHandle};
+
+use datalink_bridge_config::MemMapConfig;
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::{CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, HANDLE, WAIT_FAILED, WAIT_OBJECT_0},
+        Storage::FileSystem::FILE_ATTRIBUTE_TEMPORARY,
+        System::Threading::{CreateEventW, WaitForMultipleObjects},
+    },
+};
+
+/// Limit of WaitForMultipleObjects
+const MAXIMUM_WAIT_OBJECTS: usize = 64;
+
+/// How often the waiting thread checks if it should shut down
+const WAIT_TIMEOUT_MS: u32 = 250;
+
+/// A named event and the sequence counter file it is bumping
+struct EventCounter {
+    name: String,
+    handle: HANDLE,
+    file: File,
+    path: PathBuf,
+    count: u32
+}
+
+// The handle is only ever used by the waiting thread after creation
+unsafe impl Send for EventCounter {}
+
+impl EventCounter {
+    fn bump(&mut self) {
+        self.count = self.count.wrapping_add(1);
+
+        // Not much we can do if this fails, the next signal will try again
+        let _ = self.file.seek_write(&self.count.to_le_bytes(), 0);
+    }
+}
+
+impl Drop for EventCounter {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.handle) };
+        let _ = fs::remove_file(self.path.as_path());
+    }
+}
+
+/// Waits on all configured events in a background thread.
+///
+/// Dropping it stops the thread and removes the sequence files.
+pub struct EventBridge {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>
+}
+
+impl Drop for EventBridge {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Creates the events of all maps that have one configured, and starts the thread waiting on them.
+///
+/// Events that already exist are skipped (see the module docs).
+/// Returns None if no map has an event set (or none could be bridged)
+pub(crate) fn create_event_bridge(dir: PathBuf, maps: &[MemMapConfig]) -> Result<Option<EventBridge>, String> {
+    let mut counters = Vec::<EventCounter>::new();
+
+    for map in maps {
+        let event = if let Some(event) = map.event.as_ref() {
+            event
+        } else {
+            continue;
+        };
+
+        if counters.len() >= MAXIMUM_WAIT_OBJECTS {
+            return Err(format!("Only up to {MAXIMUM_WAIT_OBJECTS} events are supported, {event} exceeds this"));
+        }
+
+        // Auto reset, as we are the one waiting on it
+        let name = HSTRING::from(event.as_str());
+        let handle = unsafe { CreateEventW(None, false, false, &name) }
+            .map_err(|e| format!("Failed to create event {event}: {e}"))?;
+        if unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+            let _ = unsafe { CloseHandle(handle) };
+            println!("Event {event} for MemoryMap {} already exists, it is not bridged as we don't own it", map.name);
+            continue;
+        }
+
+        let mut path = dir.clone();
+        path.push(format!("{}.seq", map.name));
+
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .attributes(FILE_ATTRIBUTE_TEMPORARY.0)
+            .create(true)
+            .truncate(true)
+            .open(path.as_path())
+            .map_err(|_| format!("Could not open the tmpfs file: {path:?}"))?;
+        file.seek_write(&0_u32.to_le_bytes(), 0).map_err(|e| format!("Could not write the tmpfs file {path:?}: {e}"))?;
+
+        println!("Bridging event {event} for MemoryMap {} successfully", map.name);
+        counters.push(EventCounter { name: event.clone(), handle, file, path, count: 0 });
+    }
+
+    if counters.is_empty() {
+        return Ok(None);
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    let flag = running.clone();
+
+    let thread = std::thread::spawn(move || {
+        let handles: Vec<HANDLE> = counters.iter().map(|c| c.handle).collect();
+
+        while flag.load(Ordering::Relaxed) {
+            let res = unsafe { WaitForMultipleObjects(&handles, false, WAIT_TIMEOUT_MS) };
+
+            if res == WAIT_FAILED {
+                println!("Waiting on the events failed, stopping event bridge");
+                break;
+            }
+
+            let index = res.0.wrapping_sub(WAIT_OBJECT_0.0) as usize;
+            if let Some(counter) = counters.get_mut(index) {
+                // Our events are auto reset, so the wait already consumed the signal
+                counter.bump();
+            }
+            // Anything else is the timeout, so we check if we are still running
+        }
+
+        for counter in counters.iter() {
+            println!("Event {} was signaled {} times", counter.name, counter.count);
+        }
+    });
+
+    Ok(Some(EventBridge { running, thread: Some(thread) }))
+}
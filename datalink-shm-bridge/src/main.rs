@@ -1,18 +1,18 @@
 #![cfg_attr(not(feature = "display-console"), windows_subsystem = "windows")]
 
-use std::time::Duration;
-use datalink_bridge_config::{AppContainer, GameBridgeConfig};
+use std::{sync::atomic::AtomicBool, time::Duration};
+use datalink_bridge_config::{overlay::OverlaySession, template::{self, TemplateVars}, GameBridgeConfig, Overlay, ProcessTracking, StartTrigger};
 use mmap::FileMapping;
 
 mod mmap;
 
-mod presets;
+mod apps;
 
-mod process_detection;
+mod events;
 
-mod built_info {
-    include!(concat!(env!("OUT_DIR"), "/built.rs"));
-}
+mod registry;
+
+mod process_detection;
 
 #[cfg(feature = "display-console")]
 const DELAY: Duration = Duration::from_secs(5);
@@ -31,13 +31,35 @@ fn main() {
     
     let callback = expect_exit(args.nth(1), "Missing argument, expected callback path");
     let game_id = expect_exit(args.next(), "Missing argument, expected game_id");
+    // The Steam AppId, Datalink keys its per game configs (like the udp relay) with it, not the reported names
+    let app_id = game_id.clone();
 
     let game_exe = expect_exit(args.next(), "Missing argument, expected game executable");
     
-    // Reading the config
-    let (callback, game_exe, game_id, maps, apps, post_apps) = match datalink_bridge_config::read_config(presets::get_preset(game_id.as_str())) { // The LSP pretends the function does not exist
+    // Drive letter of the linux root, as Datalink found it in the prefix (configs can still override it)
+    let detected_root = datalink_bridge_config::get_detected_root_mount_point();
+    let default_root = detected_root.unwrap_or(GameBridgeConfig::default().get_root_mount_point());
+
+    // Reading the config (checked against the policy on the linux side, if set)
+    // The policy is a linux path, and we have no config yet that could override the drive letter
+    let policy = datalink_bridge_config::policy::read_policy(default_root);
+    let (config, err, mut report) = datalink_bridge_config::read_config_with_report(datalink_bridge_config::presets::get_preset(game_id.as_str()), policy.as_ref()); // The LSP pretends the function does not exist
+
+    // Where each map/app came from, written next to the configs
+    #[cfg(debug_assertions)]
+    println!("{report}");
+    if let Some(folder) = datalink_bridge_config::get_config_folder_path().filter(|f| f.exists()) {
+        if let Err(e) = report.write(folder.as_path()) {
+            println!("Failed to write merge report: {e}");
+        }
+    }
+
+    // Registry changes a crashed session left behind (overlays are restored with start_overlays)
+    registry::restore_previous_session();
+
+    let (callback, game_exe, game_id, root, tracking, trigger, maps, events, overlays, mut apps, post_apps) = match (config, err) {
         (Some((config, alt)), err) => {
-            let config: GameBridgeConfig = config; // We can at least code with this still
+            let mut config: GameBridgeConfig = config; // We can at least code with this still
 
             let mut game_names = if let Some(list) = alt {
                 list
@@ -77,12 +99,48 @@ fn main() {
                 println!("Errors Occured during Reading:\n{e}\nContinuing (but configuration might be wrong)");
             }
 
+            if let Some(warning) = detected_root.and_then(|detected| config.apply_detected_root_mount_point(detected)) {
+                println!("Warning: {warning}");
+            }
+
 
             // Memory maps
             let tmpfs = match mmap::get_tmpfs_mountpoint(config.get_root_mount_point()) {
                 Some(p) => p,
                 None => error_exit("Unable to find /dev/shm through the wine prefix")
             };
+
+            // Placeholders, then the policy can check the resulting paths
+            let vars = template_vars(game_names[0].as_str(), convert_linux_path(config.get_root_mount_point(), game_exe.clone()), &tmpfs);
+            let unknown = config.expand_templates(&vars);
+            if !unknown.is_empty() {
+                println!("Unresolved placeholders (kept as they are):\n{}", unknown.join("\n"));
+
+                // The report was already written, but these are most likely typos worth seeing there
+                report.warnings.extend(unknown);
+                if let Some(folder) = datalink_bridge_config::get_config_folder_path().filter(|f| f.exists()) {
+                    if let Err(e) = report.write(folder.as_path()) {
+                        println!("Failed to write merge report: {e}");
+                    }
+                }
+            }
+
+            if let Some(policy) = policy.as_ref() {
+                policy.filter_paths(&mut config);
+
+                // Dry run, only report what would be done
+                if policy.is_dry_run() {
+                    if let Some(folder) = datalink_bridge_config::get_config_folder_path() {
+                        println!("Policy dry run, the configs would do:\n{}", policy.report(folder.as_path(), &vars));
+                    }
+                    println!("No apps, actions and overlays will be run");
+
+                    config.apps.clear();
+                    config.post_apps.clear();
+                    config.overlays.clear();
+                }
+            }
+
             let mut maps = Vec::<FileMapping>::with_capacity(config.maps.len());
 
             for item in &config.maps {
@@ -98,23 +156,32 @@ fn main() {
                 }
             }
 
-            // Apps
+            // Data ready events
+            let events = match events::create_event_bridge(tmpfs.clone(), &config.maps) {
+                Ok(events) => events,
+                Err(e) => {
+                    drop(maps);
+                    error_exit(format!("Failed to bridge events: {e}").as_str())
+                }
+            };
+
+            // Overlays, before the apps as they might rely on them
             let root = config.get_root_mount_point();
-            let mut apps = Vec::<std::process::Child>::with_capacity(config.apps.len());
+            let overlays = start_overlays(root, &config.overlays);
 
-            for item in config.apps {
-                match perform_side_app(root, item) {
-                    Ok(None) => (),
-                    Ok(Some(c)) => apps.push(c),
-                    Err(e) => {
-                        // Cleanup already created maps
-                        drop(maps);
-                        close_apps(apps);
+            // Apps
+            let apps = match apps::launch_apps(root, config.apps, &maps) {
+                Ok(apps) => apps,
+                Err(e) => {
+                    // Cleanup already created maps, overlays and registry changes
+                    restore_overlays(overlays);
+                    registry::restore();
+                    drop(events);
+                    drop(maps);
 
-                        error_exit(e.as_str());
-                    }
+                    error_exit(e.as_str());
                 }
-            }
+            };
 
             let post_apps = if config.post_apps.is_empty() {
                 None
@@ -122,7 +189,7 @@ fn main() {
                 Some((config.post_apps, root))
             };
 
-            (convert_linux_path(root, callback), convert_linux_path(root, game_exe), game_names, maps, apps, post_apps)
+            (convert_linux_path(root, callback), convert_linux_path(root, game_exe), game_names, root, config.process_tracking, config.start_trigger, maps, events, overlays, apps, post_apps)
         },
         (None, Ok(())) => {
             println!("{} starting...", game_id.as_str());
@@ -130,9 +197,10 @@ fn main() {
             println!("No Config File Found!");
             println!("No Memory Maps and Apps will be deployed, dbus will still be notified!");
 
-            let root = datalink_bridge_config::GameBridgeConfig::default().get_root_mount_point();
+            let root = default_root;
+            let overlays = start_overlays(root, &[]); // Still restoring what a crashed session left behind
 
-            (convert_linux_path(root, callback), convert_linux_path(root, game_exe), vec![game_id], Vec::<FileMapping>::new(), Vec::<std::process::Child>::new(), None)
+            (convert_linux_path(root, callback), convert_linux_path(root, game_exe), vec![game_id], root, ProcessTracking::default(), StartTrigger::default(), Vec::<FileMapping>::new(), None, overlays, apps::RunningApps::default(), None)
         },
         (None, Err(e)) => {
             println!("{} starting...", game_id.as_str());
@@ -141,9 +209,10 @@ fn main() {
             println!("{e}");
             println!("No Memory Maps and Apps will be deployed, dbus will still be notified!");
 
-            let root = datalink_bridge_config::GameBridgeConfig::default().get_root_mount_point();
+            let root = default_root;
+            let overlays = start_overlays(root, &[]); // Still restoring what a crashed session left behind
 
-            (convert_linux_path(root, callback), convert_linux_path(root, game_exe), vec![game_id], Vec::<FileMapping>::new(), Vec::<std::process::Child>::new(), None)
+            (convert_linux_path(root, callback), convert_linux_path(root, game_exe), vec![game_id], root, ProcessTracking::default(), StartTrigger::default(), Vec::<FileMapping>::new(), None, overlays, apps::RunningApps::default(), None)
         }
     };
 
@@ -155,17 +224,30 @@ fn main() {
     }
     
     // Pre-Game dbus message
-    for name in game_id.iter() {
-        send_dbus(callback.as_str(), "--set-playing", name.as_str());
+    // We can't wait for the callback, so separate calls could arrive in any order. Launching and
+    // playing at once are sent by the same call instead
+    for (index, name) in game_id.iter().enumerate() {
+        if trigger == StartTrigger::Immediately {
+            send_playing(callback.as_str(), "--set-launching-playing", name.as_str(), index == 0, app_id.as_str());
+        } else {
+            send_dbus(callback.as_str(), "--set-launching", name.as_str());
+        }
     }
 
 
-    // Launching the game
+    // Launching the game, suspended until it is in the job (so everything it spawns is tracked)
     println!("DO NOT CLOSE THIS WINDOW!");
+    std::os::windows::process::CommandExt::creation_flags(&mut cmd, windows::Win32::System::Threading::CREATE_SUSPENDED.0);
     let mut pro = match cmd.spawn() {
         Ok(pro) => pro,
         Err(e) => error_exit(format!("Failure to launch game: {e}").as_str())
     };
+    let job = process_detection::GameJob::new(&pro);
+    if let Err(e) = process_detection::resume(&pro) {
+        // A game stuck suspended would never exit, so the session is ended like the game crashed
+        println!("Failed to resume the game: {e}");
+        let _ = pro.kill();
+    }
 
     // ctrlc::set_handler(|| {
     //     
@@ -173,9 +255,26 @@ fn main() {
     //
     // }).expect("Interrupt handler should never fail to be created");
     
-    let _ = pro.wait();
+    let started = if trigger == StartTrigger::Immediately {
+        true
+    } else if process_detection::wait_for_start(&trigger, &mut pro, game_exe.as_str(), root, &tracking, &maps) {
+        for (index, name) in game_id.iter().enumerate() {
+            send_playing(callback.as_str(), "--set-playing", name.as_str(), index == 0, app_id.as_str());
+        }
+        true
+    } else {
+        println!("The game exited before it started");
+        false
+    };
+
+    if started {
+        if !apps.is_empty() {
+            apps.watch(root, callback.clone(), game_id.clone());
+        }
 
-    process_detection::poll_game(game_exe);
+        process_detection::wait_for_exit(&mut pro, job.as_ref(), game_exe, root, &tracking);
+    }
+    drop(job);
     
     
     // Game closed, wrapping up
@@ -185,25 +284,30 @@ fn main() {
     }
     if !apps.is_empty() {
         println!("Terminating auxilary apps...");
-        if close_apps(apps) {
+        if apps.close(root) {
             
         }
     }
+    restore_overlays(overlays);
+    registry::restore(); // Session is over, so are the registry changes
+    drop(events); // Stops waiting on the events, and removes the sequence files
     drop(maps); // Maps and their files are cleaned up on drop
 
     // Post App for cleanup purposes
     if let Some((post_apps,root)) = post_apps {
         println!("Running clean up apps");
 
-        let mut clean_the_cleaners = Vec::<std::process::Child>::with_capacity(post_apps.len());
+        let mut clean_the_cleaners = Vec::<(datalink_bridge_config::App, std::process::Child)>::with_capacity(post_apps.len());
+        // The game already exited, so only the wait timeout stops waiting for post apps
+        let running = AtomicBool::new(true);
         for app in post_apps {
-            match perform_side_app(root, app) {
+            match apps::perform_side_app(root, app, &running) {
                 Ok(None) => (),
-                Ok(Some(mut child)) => {
+                Ok(Some((app, mut child))) => {
                     if let Ok(Some(_)) = child.try_wait() {
                         // Already exited, no need to wait
                     } else {
-                        clean_the_cleaners.push(child);
+                        clean_the_cleaners.push((app, child));
                     }
                 },
                 Err(e) => {
@@ -212,12 +316,15 @@ fn main() {
             }
         }
 
-        // We let them execute for 1s
-        std::thread::sleep(Duration::from_secs(1));
+        // Apps set to wait already ran to completion, the rest we let execute for 1s
+        if !clean_the_cleaners.is_empty() {
+            std::thread::sleep(Duration::from_secs(1));
 
-        let _ = close_apps(clean_the_cleaners);
+            let _ = apps::close_apps(root, clean_the_cleaners);
+        }
     }
 
+    registry::restore(); // In case post apps set any
     println!("Shutdown finished, window should close now");
 
 
@@ -225,108 +332,60 @@ fn main() {
     std::thread::sleep(DELAY);
 }
 
-fn convert_linux_path(drive_letter: char, path: String) -> String {
-    // The LSP pretends the function does not exist
-    // But it does under windows, for which we compile it
-    datalink_bridge_config::convert_linux_path_to_wine(drive_letter, path)
-}
+/// Values for the placeholders in apps, actions and overlays
+fn template_vars(game_id: &str, game_exe: String, tmpfs: &std::path::Path) -> TemplateVars {
+    let mut vars = TemplateVars::default()
+        .with(template::GAME_ID, game_id.to_string())
+        .with(template::SHM_DIR, tmpfs.to_string_lossy().to_string())
+        .with(template::SESSION_ID, format!("{}-{}", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs(), std::process::id()));
 
-fn perform_side_app(root: char, app: AppContainer) -> Result<Option<std::process::Child>, String> {
-    match app {
-        AppContainer::App(app) => start_side_app(root, app).map(|child| Some(child)),
-        AppContainer::Action(action) => {
-            match action.perform(root) {
-                Ok(()) => Ok(None),
-                Err(e) => Err(e.to_string())
-            }
-        }
+    if let Some(dir) = std::path::Path::new(game_exe.as_str()).parent() {
+        vars = vars.with(template::GAME_DIR, dir.to_string_lossy().to_string());
     }
-}
-
-/// Starts another app on the side
-fn start_side_app(root: char, app: datalink_bridge_config::App) -> Result<std::process::Child, String> {
-    let name = app.get_name().to_string();
+    vars = vars.with(template::GAME_EXE, game_exe);
 
-    let mut cmd: std::process::Command = app.get_command(root).ok_or(format!("Failed to generate command for App {name}"))?;
+    if let Some(dir) = datalink_bridge_config::get_config_folder_path() {
+        vars = vars.with(template::CONFIG_DIR, dir.to_string_lossy().to_string());
+    }
 
-    let child = cmd.spawn().map_err(|e|  format!("Failed to spawn process for App {name}: {}", e.to_string()))?;
-    println!("Successfully launched App {name}");
-    Ok(child)
+    vars
 }
 
-const CLOSING_POLLING_RATE: Duration = Duration::from_millis(250);
-const CLOSING_POLLING_COUNT: usize = 20;
-
-/// Tries to close the children, but if one won't we try the others and return false to signal not
-/// complete (but likely sufficient cleanup)
-fn close_apps(mut apps: Vec<std::process::Child>) -> bool {
-    let mut clean = true;
-    let mut closed = true;
-
-    for item in apps.iter_mut() {
-        match item.try_wait() {
-            Ok(Some(_)) => (),
-            Ok(None) => {
-                let mut killer = std::process::Command::new("taskkill");
-                killer.arg("/pid");
-                killer.arg(item.id().to_string());
-
-                let _ = killer.spawn();
-                closed = false;
-            },
-            Err(e) => {
-                let _ = e;
-                clean = false;
-            }
-        }
-    }
+/// Restores the overlays a crashed session left behind, then applies the given ones
+fn start_overlays(root: char, overlays: &[Overlay]) -> Option<OverlaySession> {
+    let folder = datalink_bridge_config::get_config_folder_path()?;
 
+    let (mut session, res) = OverlaySession::start(folder);
+    if let Err(e) = res {
+        println!("Errors occured restoring the overlays of the previous session:{e}");
+    }
 
-    // Waiting for gracefull termination
-    for _ in 0..CLOSING_POLLING_COUNT {
-        if closed {
-            return clean;
+    for overlay in overlays {
+        if let Err(e) = overlay.apply(root, &mut session) {
+            println!("Unable to apply overlay: {e}");
         }
+    }
 
-        closed = true;
-        let start = std::time::Instant::now();
+    Some(session)
+}
 
-        for item in apps.iter_mut() {
-            match item.try_wait() {
-                Ok(None) => {
-                    closed = false;
-                    break;
-                },
-                _ => ()
-            }
+fn restore_overlays(session: Option<OverlaySession>) {
+    if let Some(session) = session {
+        if session.is_empty() {
+            return;
         }
 
-        if let Some(time) = CLOSING_POLLING_RATE.checked_sub(std::time::Instant::now() - start) {
-            std::thread::sleep(time);
-        }
-    }
-    
-    // Forcefull termination
-    for item in apps.iter_mut() {
-        match item.try_wait() {
-            Ok(None) => {
-                println!("A app reached timeout for graceful shutdown, forcefull shutdown used");
-
-                let mut killer = std::process::Command::new("taskkill");
-                killer.arg("/f");
-                killer.arg("/pid");
-                killer.arg(item.id().to_string());
-
-                let _ = killer.spawn();
-            },
-            _ => ()
+        println!("Restoring overlayed files");
+        if let Err(e) = session.restore() {
+            println!("Errors occured restoring overlays:{e}");
         }
     }
+}
 
-    std::thread::sleep(std::time::Duration::from_secs(2));
-
-
-    clean
+fn convert_linux_path(drive_letter: char, path: String) -> String {
+    // The LSP pretends the function does not exist
+    // But it does under windows, for which we compile it
+    datalink_bridge_config::convert_linux_path_to_wine(drive_letter, path)
 }
 
 /// Unwraps value with our error handler
@@ -363,3 +422,30 @@ fn send_dbus(callback: &str, op: &str, game_id: &str) {
     let _ = cmd.spawn();
 }
 
+/// Sends playing (or launching and playing), with the AppId for the udp relay if `relay` is set
+///
+/// Only one of the names should start the relay, as they would all listen on the same port
+fn send_playing(callback: &str, op: &str, game_id: &str, relay: bool, app_id: &str) {
+    // Same as send_dbus, we can not know if it succeeded
+    let mut cmd = std::process::Command::new(callback);
+    cmd.arg(op);
+    cmd.arg(game_id);
+    if relay {
+        cmd.arg(app_id);
+    }
+
+    let _ = cmd.spawn();
+}
+
+/// Reports an auxiliary app that exited with a failure (-1 as exit_code if unknown)
+pub(crate) fn send_app_crashed(callback: &str, game_id: &str, app: &str, exit_code: i32) {
+    // Same as send_dbus, we can not know if it succeeded
+    let mut cmd = std::process::Command::new(callback);
+    cmd.arg("--app-crashed");
+    cmd.arg(game_id);
+    cmd.arg(app);
+    cmd.arg(exit_code.to_string());
+
+    let _ = cmd.spawn();
+}
+
@@ -22,7 +22,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::{fs::{self, File}, os::windows::{fs::OpenOptionsExt,prelude::AsRawHandle}, path::PathBuf, str::FromStr};
+use std::{fs::{self, File}, os::windows::{fs::OpenOptionsExt,prelude::AsRawHandle}, path::{Path, PathBuf}, str::FromStr};
 use windows::{
     core::HSTRING,
     Wdk::System::SystemServices::PAGE_READWRITE,
@@ -62,6 +62,7 @@ pub fn get_tmpfs_mountpoint(root: char) -> Option<PathBuf> {
 /// [1]: https://learn.microsoft.com/en-us/windows/win32/memory/creating-named-shared-memory
 pub struct FileMapping {
     handle: HANDLE,
+    name: String,
     path: PathBuf
 }
 
@@ -88,6 +89,7 @@ impl FileMapping {
 
         // Windows uses UTF-16, so we need to convert the UTF-8 based Rust string
         // accordingly.
+        let map_name = name.to_string();
         let name = HSTRING::from(name);
         let handle = HANDLE(file.as_raw_handle() as _);
 
@@ -103,12 +105,39 @@ impl FileMapping {
         };
 
         match handle {
-            Ok(handle) => Ok(FileMapping { handle, path }),
+            Ok(handle) => Ok(FileMapping { handle, name: map_name, path }),
             Err(e) => Err(format!("Failed to create the FileMapping: {e}")),
         }
     }
 }
 
+impl FileMapping {
+    /// The name the Windows applications use for this map
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The path of the tmpfs file backing this map
+    pub fn get_path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    /// Checks if anything was written into the map yet (any byte not zero).
+    pub fn has_data(&self) -> bool {
+        file_has_data(self.path.as_path())
+    }
+}
+
+/// Checks if anything was written into the map backed by this tmpfs file (any byte not zero).
+///
+/// As the map is backed by the tmpfs file, we can just read the file
+pub fn file_has_data(path: &Path) -> bool {
+    match fs::read(path) {
+        Ok(data) => data.iter().any(|b| *b != 0),
+        Err(_) => false
+    }
+}
+
 impl Drop for FileMapping {
     fn drop(&mut self) {
         // There's not much we can do if an error happens here, so let's ignore it.
@@ -2,40 +2,334 @@
 //! In that case we need to insure the game has closed and is not magically running in the
 //! background still.
 
-use std::{path::{Path, PathBuf}, str::FromStr, time::Duration};
+use std::{os::windows::io::AsRawHandle, path::PathBuf, process::Child, str::FromStr, time::{Duration, Instant}};
 
-use sysinfo::{self, Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use datalink_bridge_config::{ProcessTracking, StartTrigger};
+use sysinfo::{self, Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use windows::{
+    core::{HRESULT, PCWSTR},
+    Win32::{
+        Foundation::{CloseHandle, ERROR_MORE_DATA, HANDLE, WAIT_FAILED},
+        System::{
+            Diagnostics::ToolHelp::{CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32},
+            JobObjects::{
+                AssignProcessToJobObject, CreateJobObjectW, JobObjectBasicProcessIdList, JobObjectExtendedLimitInformation, QueryInformationJobObject,
+                SetInformationJobObject, JOBOBJECT_BASIC_LIMIT_INFORMATION, JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_BREAKAWAY_OK
+            },
+            Threading::{OpenProcess, OpenThread, ResumeThread, WaitForMultipleObjects, INFINITE, PROCESS_SYNCHRONIZE, THREAD_SUSPEND_RESUME},
+        },
+    },
+};
 
-const POLL_RATE: Duration = Duration::from_secs(5);
+use crate::mmap::FileMapping;
 
-pub fn poll_game(game_exe: String) -> Option<()> {
-    println!("The initial Process of the game has closed, falling back to polling...");
+/// Limit of WaitForMultipleObjects, and how many process ids we first try to read from the job
+const MAXIMUM_WAIT_OBJECTS: usize = 64;
+
+/// How often we grow the buffer for the process ids, in case more processes join the job meanwhile
+const PROCESS_ID_ATTEMPTS: usize = 4;
+
+/// JOBOBJECT_BASIC_PROCESS_ID_LIST starts with two u32 counts, followed by the ids (as usize)
+const PROCESS_ID_LIST_HEADER: usize = 2 * std::mem::size_of::<u32>() / std::mem::size_of::<usize>();
+
+/// Job object the game is put into, so processes spawned by the game are tracked as part of
+/// the same session (even if they live outside of the game folder)
+pub struct GameJob {
+    handle: HANDLE
+}
+
+impl GameJob {
+    /// Creates a job and assigns the launched process to it.
+    ///
+    /// The process has to be spawned suspended (and resumed with `resume` after this), else it
+    /// might spawn processes before it is in the job, which are then only found through the tracking rules.
+    ///
+    /// Breakaway is allowed, so launchers and anti cheat that spawn with `CREATE_BREAKAWAY_FROM_JOB`
+    /// don't fail to start (these are then only found through the tracking rules too)
+    pub fn new(launched: &Child) -> Option<Self> {
+        let handle = unsafe { CreateJobObjectW(None, PCWSTR::null()) }.ok()?;
+        let job = GameJob { handle };
+
+        let limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION {
+            BasicLimitInformation: JOBOBJECT_BASIC_LIMIT_INFORMATION { LimitFlags: JOB_OBJECT_LIMIT_BREAKAWAY_OK, ..Default::default() },
+            ..Default::default()
+        };
+        let res = unsafe {
+            SetInformationJobObject(
+                job.handle,
+                JobObjectExtendedLimitInformation,
+                &limits as *const JOBOBJECT_EXTENDED_LIMIT_INFORMATION as _,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32
+            )
+        };
+        if let Err(e) = res {
+            // Without breakaway processes asking for it would fail to launch, so we rather not use the job
+            println!("Unable to track the child processes of the game, failed to allow breakaway: {e}");
+            return None;
+        }
+
+        if let Err(e) = unsafe { AssignProcessToJobObject(job.handle, HANDLE(launched.as_raw_handle() as _)) } {
+            println!("Unable to track the child processes of the game: {e}");
+            return None;
+        }
+
+        Some(job)
+    }
+
+    /// Returns the ids of all processes currently running within the job
+    fn get_pids(&self) -> Vec<u32> {
+        let more_data = HRESULT::from_win32(ERROR_MORE_DATA.0);
+        let mut capacity = MAXIMUM_WAIT_OBJECTS;
+
+        for _ in 0..PROCESS_ID_ATTEMPTS {
+            // usize, so the ids are aligned
+            let mut list = vec![0_usize; PROCESS_ID_LIST_HEADER + capacity];
+
+            let res = unsafe {
+                QueryInformationJobObject(
+                    Some(self.handle),
+                    JobObjectBasicProcessIdList,
+                    list.as_mut_ptr() as _,
+                    std::mem::size_of_val(list.as_slice()) as u32,
+                    None
+                )
+            };
+
+            let counts = list.as_ptr() as *const u32;
+            let (assigned, in_list) = unsafe { (*counts as usize, *counts.add(1) as usize) };
+
+            match res {
+                Ok(()) => return list.iter().skip(PROCESS_ID_LIST_HEADER).take(in_list.min(capacity)).map(|id| *id as u32).collect(),
+                // More processes than fit, with some headroom in case more join until the next try
+                Err(e) if e.code() == more_data => capacity = assigned.max(capacity) * 2,
+                Err(_) => return Vec::new()
+            }
+        }
+
+        Vec::new()
+    }
+}
+
+/// Resumes all threads of a process that was spawned suspended
+pub fn resume(launched: &Child) -> Result<(), String> {
+    let pid = launched.id();
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) }.map_err(|e| e.to_string())?;
+
+    let mut entry = THREADENTRY32 { dwSize: std::mem::size_of::<THREADENTRY32>() as u32, ..Default::default() };
+    let mut resumed = 0_usize;
+    let mut res = unsafe { Thread32First(snapshot, &mut entry) };
+    while res.is_ok() {
+        if entry.th32OwnerProcessID == pid {
+            if let Ok(thread) = unsafe { OpenThread(THREAD_SUSPEND_RESUME, false, entry.th32ThreadID) } {
+                if unsafe { ResumeThread(thread) } != u32::MAX {
+                    resumed += 1;
+                }
+                let _ = unsafe { CloseHandle(thread) };
+            }
+        }
+        res = unsafe { Thread32Next(snapshot, &mut entry) };
+    }
+    let _ = unsafe { CloseHandle(snapshot) };
+
+    if resumed == 0 {
+        return Err(format!("No thread of process {pid} could be resumed"));
+    }
+    Ok(())
+}
+
+impl Drop for GameJob {
+    fn drop(&mut self) {
+        // The job does not kill on close, so the processes just leave the job
+        let _ = unsafe { CloseHandle(self.handle) };
+    }
+}
+
+/// Waits for the game to exit, by blocking on handles of the launched process and all further
+/// game processes (from the job and the tracking rules), until none can be found anymore.
+///
+/// If a handle can not be opened we fall back to polling
+pub fn wait_for_exit(launched: &mut Child, job: Option<&GameJob>, game_exe: String, root: char, tracking: &ProcessTracking) -> Option<()> {
+    let _ = launched.wait();
+    println!("The initial Process of the game has closed, waiting on remaining game processes...");
+
+    let mut path = PathBuf::from_str(game_exe.as_str()).ok()?;
+    path.pop();
+    let folders = tracking.get_folders(root, path.as_path());
+
+    let grace = tracking.get_grace_period();
+
+    let mut system = System::new();
+    let mut in_grace = false;
+    loop {
+        let mut pids = job.map(|j| j.get_pids()).unwrap_or_default();
+
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing()
+                .with_exe(UpdateKind::OnlyIfNotSet)
+        );
+        for pro in system.processes().values().filter(|val| is_game_process(val, &folders, tracking)) {
+            let pid = pro.pid().as_u32();
+            if !pids.contains(&pid) {
+                pids.push(pid);
+            }
+        }
+
+        if pids.is_empty() {
+            if in_grace || grace.is_zero() {
+                println!("No Game Process can be found anymore");
+                return Some(());
+            }
+
+            // The game might be relaunching itself
+            in_grace = true;
+            std::thread::sleep(grace);
+            continue;
+        }
+        in_grace = false;
+
+        let mut handles = Vec::<HANDLE>::with_capacity(pids.len());
+        for pid in pids {
+            match unsafe { OpenProcess(PROCESS_SYNCHRONIZE, false, pid) } {
+                Ok(handle) => handles.push(handle),
+                Err(e) => {
+                    println!("Unable to open game process {pid} ({e}), falling back to polling...");
+                    close_handles(handles);
+                    return poll_game(game_exe, root, tracking);
+                }
+            }
+        }
+
+        for chunk in handles.chunks(MAXIMUM_WAIT_OBJECTS) {
+            if unsafe { WaitForMultipleObjects(chunk, true, INFINITE) } == WAIT_FAILED {
+                println!("Waiting on the game processes failed, falling back to polling...");
+                close_handles(handles);
+                return poll_game(game_exe, root, tracking);
+            }
+        }
+        close_handles(handles);
+
+        // All processes we knew exited, but they might have spawned new ones in the meantime
+    }
+}
+
+fn close_handles(handles: Vec<HANDLE>) {
+    for handle in handles {
+        let _ = unsafe { CloseHandle(handle) };
+    }
+}
+
+/// How often we check if the game has started yet
+const START_POLL_RATE: Duration = Duration::from_millis(500);
+
+/// Waits until the game has started according to the trigger.
+///
+/// Returns false if the game exited before it ever started (e.g. the user quit from the launcher)
+pub fn wait_for_start(trigger: &StartTrigger, launched: &mut Child, game_exe: &str, root: char, tracking: &ProcessTracking, maps: &[FileMapping]) -> bool {
+    let map = if let StartTrigger::MapData { map } = trigger {
+        if let Some(m) = maps.iter().find(|m| m.get_name() == map) {
+            Some(m)
+        } else {
+            println!("Start trigger map {map} is not a configured memory map, considering the game started");
+            return true;
+        }
+    } else {
+        None
+    };
+
+    let mut path = if let Ok(path) = PathBuf::from_str(game_exe) {
+        path
+    } else {
+        return true;
+    };
+    path.pop();
+    let folders = tracking.get_folders(root, path.as_path());
+
+    println!("Waiting for the game to start...");
+
+    let grace = tracking.get_grace_period();
+    let mut system = System::new();
+    let mut pid: Option<Pid> = None;
+    let mut last_seen = Instant::now();
+    loop {
+        let launched_running = matches!(launched.try_wait(), Ok(None));
+
+        // Scanning the processes is only necessary if the trigger needs it, or the launched
+        // process exited (and we need to know if the game is still around)
+        if matches!(trigger, StartTrigger::Process) || !launched_running {
+            pid = check_for_program_running(&mut system, pid, &folders, tracking);
+        }
+
+        let started = match (trigger, map) {
+            (_, Some(map)) => map.has_data(),
+            (StartTrigger::Process, _) => pid.is_some(),
+            _ => true
+        };
+
+        if started {
+            return true;
+        }
+
+        if launched_running || pid.is_some() {
+            last_seen = Instant::now();
+        } else if last_seen.elapsed() >= grace {
+            return false;
+        }
+
+        std::thread::sleep(START_POLL_RATE);
+    }
+}
+
+pub fn poll_game(game_exe: String, root: char, tracking: &ProcessTracking) -> Option<()> {
+    println!("Polling for the game processes...");
 
     let mut path = PathBuf::from_str(game_exe.as_str()).ok()?;
     path.pop();
-    let folder = path.as_path();
+    let folders = tracking.get_folders(root, path.as_path());
+
+    let poll_rate = tracking.get_poll_interval();
+    let grace = tracking.get_grace_period();
 
     let mut system = System::new();
     let mut pid: Option<Pid> = None;
+    let mut last_seen = Instant::now();
     loop {
-        pid = check_for_program_running(&mut system, pid, folder);
+        pid = check_for_program_running(&mut system, pid, &folders, tracking);
         
-        if pid.is_none() {
+        if pid.is_some() {
+            last_seen = Instant::now();
+        } else if last_seen.elapsed() >= grace {
             println!("No Game Process can be found anymore");
             return Some(());
         }
 
-        std::thread::sleep(POLL_RATE);
+        std::thread::sleep(poll_rate);
     }
 }
 
-/// This checks if a certain process (from a certain folder) is running, and retrieves the pid.
+/// Checks if the process is one of the game's, based on the folders and the tracking rules
+fn is_game_process(pro: &Process, folders: &[PathBuf], tracking: &ProcessTracking) -> bool {
+    let path = if let Some(path) = pro.exe() {
+        path
+    } else {
+        return false;
+    };
+
+    let name_matches = path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| tracking.matches_executable(name));
+
+    name_matches && folders.iter().any(|folder| path.starts_with(folder))
+}
+
+/// This checks if a certain process (from certain folders) is running, and retrieves the pid.
 /// Passing in the previous PID will cut down on having to load and search through all processes.
 /// But in case this process is no longer running or a different process, it will do a full check
 /// for another process.
-/// PID is determined on with find and contains on the cmdline, meaning if multiple processes are
+/// PID is determined on with find and the tracking rules, meaning if multiple processes are
 /// running the same (or similar) then the first PID is grabbed
-fn check_for_program_running(system: &mut System, pid: Option<Pid>, folder: &Path) -> Option<Pid> {
+fn check_for_program_running(system: &mut System, pid: Option<Pid>, folders: &[PathBuf], tracking: &ProcessTracking) -> Option<Pid> {
     if let Some(pid) = &pid {
         // We know a pid which it ran with, so we just gather info on this one
         system.refresh_processes_specifics(
@@ -46,20 +340,18 @@ fn check_for_program_running(system: &mut System, pid: Option<Pid>, folder: &Pat
         );
 
         if let Some(pro) = system.process(*pid) {
-            if let Some(path) = pro.exe() {
-                if path.starts_with(folder) && pro.exists() {
-                    return Some(pro.pid());
-                }
+            if is_game_process(pro, folders, tracking) && pro.exists() {
+                return Some(pro.pid());
             }
 
             // There is a foreign process running under our ID, we retry
             #[cfg(debug_assertions)]
             println!("Process closed/switched?");
-            return check_for_program_running(system, None, folder);
+            return check_for_program_running(system, None, folders, tracking);
         }
 
         // Our process is gone, but don't worry, maybe he is still out there under another pid
-        return check_for_program_running(system, None, folder);
+        return check_for_program_running(system, None, folders, tracking);
     } else {
         // We scan all processes
         system.refresh_processes_specifics(
@@ -69,13 +361,7 @@ fn check_for_program_running(system: &mut System, pid: Option<Pid>, folder: &Pat
                 .with_exe(UpdateKind::OnlyIfNotSet)
         );
 
-        if let Some(pro) = system.processes().values().find(|val| {
-            if let Some(path) = val.exe() {
-                path.starts_with(folder)
-            } else {
-                false
-            }
-        }) {
+        if let Some(pro) = system.processes().values().find(|val| is_game_process(val, folders, tracking)) {
             return Some(pro.pid());
         }
     }
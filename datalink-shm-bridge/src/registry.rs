@@ -0,0 +1,251 @@
+//! Registry actions, applied through the Win32 registry API.
+//!
+//! For changes with restore set the previous state is recorded, and reverted once the game exited
+//! (in reverse order, so setting the same value twice still restores the original). The previous
+//! states are journaled in the config folder before each change, so changes of a session that was
+//! killed are reverted on the next start.
+
+use std::sync::{Mutex, MutexGuard};
+
+use datalink_bridge_config::{registry::{PreviousValue, RegistryJournal}, Action, RegistryRoot, RegistryValue};
+use windows::{
+    core::{HSTRING, PCWSTR},
+    Win32::{
+        Foundation::{ERROR_FILE_NOT_FOUND, WIN32_ERROR},
+        System::Registry::{
+            RegCloseKey, RegCreateKeyExW, RegDeleteKeyW, RegDeleteValueW, RegOpenKeyExW, RegQueryValueExW, RegSetValueExW,
+            HKEY, HKEY_CLASSES_ROOT, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, HKEY_USERS, KEY_READ, KEY_WRITE,
+            REG_CREATED_NEW_KEY, REG_CREATE_KEY_DISPOSITION, REG_OPTION_NON_VOLATILE, REG_VALUE_TYPE,
+        },
+    },
+};
+
+/// Changes to revert once the game exited, opened on first use
+static JOURNAL: Mutex<Option<RegistryJournal>> = Mutex::new(None);
+
+/// An open key, closed on drop
+struct Key(HKEY);
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        let _ = unsafe { RegCloseKey(self.0) };
+    }
+}
+
+/// Performs a RegSet or RegDelete action
+pub(crate) fn perform(action: Action) -> Result<(), String> {
+    match action {
+        Action::RegSet { key, name, value, restore } => {
+            let (root, subkey) = RegistryRoot::split_key(key.as_str()).ok_or(format!("Invalid registry key {key}"))?;
+            let (handle, created_key) = create_key(root, subkey)?;
+
+            let previous = query_value(&handle, name.as_str())?;
+            if restore {
+                remember(PreviousValue { key: key.clone(), name: name.clone(), value: previous, created_key })?;
+            }
+
+            if let Err(e) = set_value(&handle, name.as_str(), value.get_type_id(), value.to_bytes().as_slice()) {
+                if restore {
+                    lock_journal().get_or_insert_with(open_journal).forget_last();
+                }
+                return Err(format!("Failed to set registry value {key}\\{name}: {e}"));
+            }
+            println!("Set registry value {key}\\{name} to {}", describe(&value));
+        },
+        Action::RegDelete { key, name, restore } => {
+            let (root, subkey) = RegistryRoot::split_key(key.as_str()).ok_or(format!("Invalid registry key {key}"))?;
+            let handle = match open_key(root, subkey)? {
+                Some(handle) => handle,
+                None => {
+                    println!("No registry key {key}, continuing");
+                    return Ok(());
+                }
+            };
+
+            let previous = match query_value(&handle, name.as_str())? {
+                Some(previous) => previous,
+                None => {
+                    println!("No registry value {key}\\{name}, continuing");
+                    return Ok(());
+                }
+            };
+            if restore {
+                remember(PreviousValue { key: key.clone(), name: name.clone(), value: Some(previous), created_key: false })?;
+            }
+
+            if let Err(e) = unsafe { RegDeleteValueW(handle.0, &HSTRING::from(name.as_str())) }.ok() {
+                if restore {
+                    lock_journal().get_or_insert_with(open_journal).forget_last();
+                }
+                return Err(format!("Failed to delete registry value {key}\\{name}: {e}"));
+            }
+            println!("Deleted registry value {key}\\{name}");
+        },
+        other => return Err(format!("{} is not a registry action", other.get_description()))
+    }
+
+    Ok(())
+}
+
+/// Reverts the changes a previous session left behind (as it was killed before it could)
+pub(crate) fn restore_previous_session() {
+    let empty = lock_journal().get_or_insert_with(open_journal).is_empty();
+    if !empty {
+        println!("Found registry changes of a previous session that did not shut down, restoring them");
+        restore();
+    }
+}
+
+/// Reverts all changes that had restore set.
+///
+/// Those that fail to revert stay in the journal, and are retried on the next start
+pub(crate) fn restore() {
+    let mut guard = lock_journal();
+    let journal = guard.get_or_insert_with(open_journal);
+    let mut failed = Vec::<PreviousValue>::new();
+
+    for change in journal.take_all().into_iter().rev() {
+        let name = format!("{}\\{}", change.key, change.name);
+
+        let res = match RegistryRoot::split_key(change.key.as_str()) {
+            Some((root, subkey)) => restore_value(root, subkey, &change),
+            None => Err("invalid key".to_string())
+        };
+
+        match res {
+            Ok(()) => println!("Restored registry value {name}"),
+            Err(e) => {
+                println!("Failed to restore registry value {name}: {e}");
+                failed.push(change);
+            }
+        }
+    }
+    failed.reverse();
+
+    if let Err(e) = journal.finish(failed) {
+        println!("Failed to update the registry journal: {e}");
+    }
+}
+
+fn restore_value(root: RegistryRoot, subkey: &str, change: &PreviousValue) -> Result<(), String> {
+    match (change.value.as_ref(), open_key(root, subkey)?) {
+        (Some((kind, data)), handle) => {
+            // If the key was deleted in the meantime it is created again, to hold the value
+            let handle = match handle {
+                Some(handle) => handle,
+                None => create_key(root, subkey)?.0
+            };
+            set_value(&handle, change.name.as_str(), *kind, data.as_slice()).map_err(|e| e.to_string())
+        },
+        (None, Some(handle)) => {
+            let res = unsafe { RegDeleteValueW(handle.0, &HSTRING::from(change.name.as_str())) };
+            drop(handle);
+
+            if change.created_key {
+                // Fails if something else put content into it, which is fine
+                let _ = unsafe { RegDeleteKeyW(root_key(root), &HSTRING::from(subkey)) };
+            }
+            ignore_not_found(res).map_err(|e| e.to_string())
+        },
+        // The key is gone, and with it the value, which is the state we restore to
+        (None, None) => Ok(())
+    }
+}
+
+/// Journals the previous value, before it is changed
+fn remember(previous: PreviousValue) -> Result<(), String> {
+    lock_journal().get_or_insert_with(open_journal).record(previous)
+        .map_err(|e| format!("Not changing the registry, as it could not be restored: {e}"))
+}
+
+fn lock_journal() -> MutexGuard<'static, Option<RegistryJournal>> {
+    match JOURNAL.lock() {
+        Ok(journal) => journal,
+        Err(e) => e.into_inner()
+    }
+}
+
+fn open_journal() -> RegistryJournal {
+    let (journal, res) = RegistryJournal::open(datalink_bridge_config::get_config_folder_path());
+    if let Err(e) = res {
+        println!("{e}");
+    }
+    journal
+}
+
+fn root_key(root: RegistryRoot) -> HKEY {
+    match root {
+        RegistryRoot::ClassesRoot => HKEY_CLASSES_ROOT,
+        RegistryRoot::CurrentUser => HKEY_CURRENT_USER,
+        RegistryRoot::LocalMachine => HKEY_LOCAL_MACHINE,
+        RegistryRoot::Users => HKEY_USERS
+    }
+}
+
+/// Opens (or creates) the key, returns if it was created
+fn create_key(root: RegistryRoot, subkey: &str) -> Result<(Key, bool), String> {
+    let mut handle = HKEY::default();
+    let mut disposition = REG_CREATE_KEY_DISPOSITION::default();
+
+    unsafe {
+        RegCreateKeyExW(root_key(root), &HSTRING::from(subkey), None, PCWSTR::null(), REG_OPTION_NON_VOLATILE,
+            KEY_READ | KEY_WRITE, None, &mut handle, Some(&mut disposition))
+    }.ok().map_err(|e| format!("Failed to open registry key {subkey}: {e}"))?;
+
+    Ok((Key(handle), disposition == REG_CREATED_NEW_KEY))
+}
+
+/// Opens the key, None if it does not exist
+fn open_key(root: RegistryRoot, subkey: &str) -> Result<Option<Key>, String> {
+    let mut handle = HKEY::default();
+
+    let res = unsafe { RegOpenKeyExW(root_key(root), &HSTRING::from(subkey), None, KEY_READ | KEY_WRITE, &mut handle) };
+    if res == ERROR_FILE_NOT_FOUND {
+        return Ok(None);
+    }
+    res.ok().map_err(|e| format!("Failed to open registry key {subkey}: {e}"))?;
+
+    Ok(Some(Key(handle)))
+}
+
+/// Returns type and data of the value, None if it does not exist
+fn query_value(key: &Key, name: &str) -> Result<Option<(u32, Vec<u8>)>, String> {
+    let name_w = HSTRING::from(name);
+    let mut kind = REG_VALUE_TYPE::default();
+    let mut size = 0_u32;
+
+    let res = unsafe { RegQueryValueExW(key.0, &name_w, None, Some(&mut kind), None, Some(&mut size)) };
+    if res == ERROR_FILE_NOT_FOUND {
+        return Ok(None);
+    }
+    res.ok().map_err(|e| format!("Failed to read registry value {name}: {e}"))?;
+
+    let mut data = vec![0_u8; size as usize];
+    unsafe { RegQueryValueExW(key.0, &name_w, None, Some(&mut kind), Some(data.as_mut_ptr()), Some(&mut size)) }
+        .ok().map_err(|e| format!("Failed to read registry value {name}: {e}"))?;
+    data.truncate(size as usize);
+
+    Ok(Some((kind.0, data)))
+}
+
+fn set_value(key: &Key, name: &str, kind: u32, data: &[u8]) -> windows::core::Result<()> {
+    unsafe { RegSetValueExW(key.0, &HSTRING::from(name), None, REG_VALUE_TYPE(kind), Some(data)) }.ok()
+}
+
+fn ignore_not_found(res: WIN32_ERROR) -> windows::core::Result<()> {
+    if res == ERROR_FILE_NOT_FOUND {
+        Ok(())
+    } else {
+        res.ok()
+    }
+}
+
+fn describe(value: &RegistryValue) -> String {
+    match value {
+        RegistryValue::String(text) | RegistryValue::ExpandString(text) => format!("\"{text}\""),
+        RegistryValue::MultiString(list) => format!("{list:?}"),
+        RegistryValue::Dword(value) => value.to_string(),
+        RegistryValue::Qword(value) => value.to_string(),
+        RegistryValue::Binary(data) => format!("{} bytes", data.len())
+    }
+}